@@ -1,4 +1,8 @@
-use bfi_lib::extract_from_capture;
+use bfi_lib::{
+    extract_from_capture_with_options, generate_capture, Bandwidth, ExtractionMode,
+    ExtractionOptions, FaultRates, FcsPolicy, GeneratorConfig, ParquetOptions,
+    DEFAULT_SOUNDING_WINDOW,
+};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -20,13 +24,99 @@ enum Commands {
         #[arg(short = 'f', long, value_name = "FILE")]
         pcap_file: PathBuf,
 
-        /// parquet output file
+        /// parquet output file, CQI-only reports go to a .cqi.parquet sibling
         #[arg(short, long, value_name = "OUTFILE")]
         out_file: PathBuf,
 
         /// Whether to print extracted variables
         #[arg(short, long)]
         print: bool,
+
+        /// Skip malformed packets instead of aborting
+        #[arg(short, long)]
+        lenient: bool,
+
+        /// Keep frames with a bad FCS instead of dropping them
+        #[arg(long)]
+        keep_bad_fcs: bool,
+
+        /// Max. time in seconds between an NDP Announcement and its reports
+        #[arg(long, default_value_t = DEFAULT_SOUNDING_WINDOW)]
+        sounding_window: f64,
+
+        /// Also store the angles in radians
+        #[arg(short, long)]
+        radians: bool,
+
+        /// Also store the reconstructed beamforming matrix V
+        #[arg(short, long)]
+        v_matrix: bool,
+    },
+    /// Generate a synthetic pcap capture of HE beamforming feedback
+    Generate {
+        /// pcap output file
+        #[arg(short, long, value_name = "OUTFILE")]
+        out_file: PathBuf,
+
+        /// Number of rows (beamformer antennas), 2 to 8
+        #[arg(long, default_value_t = 4)]
+        nr: u8,
+
+        /// Number of columns (spatial streams), 1 to Nr
+        #[arg(long, default_value_t = 2)]
+        nc: u8,
+
+        /// Bandwidth in MHz: 20, 40, 80 or 160
+        #[arg(short, long, default_value_t = 80)]
+        bandwidth: u32,
+
+        /// Subcarrier grouping Ng: 4 or 16
+        #[arg(short, long, default_value_t = 4)]
+        grouping: u8,
+
+        /// Codebook info as in the MIMO Control: 0 or 1
+        #[arg(short, long, default_value_t = 1)]
+        codebook: u8,
+
+        /// Send MU instead of SU feedback
+        #[arg(long)]
+        mu: bool,
+
+        /// Number of beamformees
+        #[arg(short = 'n', long, default_value_t = 2)]
+        stations: usize,
+
+        /// Number of soundings
+        #[arg(short, long, default_value_t = 100)]
+        soundings: usize,
+
+        /// Time between soundings in seconds
+        #[arg(long, default_value_t = 0.01)]
+        interval: f64,
+
+        /// Time between the frames of a sounding in seconds
+        #[arg(long, default_value_t = 0.0002)]
+        spacing: f64,
+
+        /// Do not start the soundings with an NDP Announcement
+        #[arg(long)]
+        no_ndpa: bool,
+
+        /// Seed of the random angles, SNRs and faults
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Fraction of reports to truncate
+        #[arg(long, default_value_t = 0.0)]
+        truncation: f64,
+
+        /// Fraction of reports with a bad FCS
+        #[arg(long, default_value_t = 0.0)]
+        bad_fcs: f64,
+
+        /// Fraction of reports with a wrong action category
+        #[arg(long, default_value_t = 0.0)]
+        wrong_category: f64,
     },
 }
 
@@ -38,18 +128,130 @@ fn main() {
             pcap_file,
             out_file,
             print,
+            lenient,
+            keep_bad_fcs,
+            sounding_window,
+            radians,
+            v_matrix,
         }) => {
-            let extracted_data = extract_from_capture(pcap_file);
+            let options = ExtractionOptions {
+                mode: match lenient {
+                    true => ExtractionMode::Lenient,
+                    false => ExtractionMode::Strict,
+                },
+                fcs_policy: match keep_bad_fcs {
+                    true => FcsPolicy::Keep,
+                    false => FcsPolicy::Drop,
+                },
+                sounding_window,
+                ..Default::default()
+            };
+
+            let (extracted_data, report) =
+                match extract_from_capture_with_options(pcap_file, &options) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        eprintln!("Data extraction failed with error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
 
             if print {
-                print!("Extracted data: {:?}\n", extracted_data)
+                println!("Extracted data: {:?}", extracted_data)
             }
 
-            if let Err(e) = extracted_data.to_parquet(out_file) {
+            print!("{}", report);
+
+            let parquet_options = ParquetOptions {
+                bfa_radians: radians,
+                v_matrix,
+            };
+            if let Err(e) = extracted_data.to_parquet_with_options(out_file, &parquet_options) {
                 print!("Writing to parquet failed with error: {}", e);
             }
             println!("Data extraction completed!\n");
         }
+        Some(Commands::Generate {
+            out_file,
+            nr,
+            nc,
+            bandwidth,
+            grouping,
+            codebook,
+            mu,
+            stations,
+            soundings,
+            interval,
+            spacing,
+            no_ndpa,
+            seed,
+            truncation,
+            bad_fcs,
+            wrong_category,
+        }) => {
+            let bandwidth = match bandwidth {
+                20 => Bandwidth::Bw20,
+                40 => Bandwidth::Bw40,
+                80 => Bandwidth::Bw80,
+                160 => Bandwidth::Bw160,
+                _ => {
+                    eprintln!("Unsupported bandwidth of {} MHz", bandwidth);
+                    std::process::exit(1);
+                }
+            };
+
+            let grouping = match grouping {
+                4 => 0,
+                16 => 1,
+                _ => {
+                    eprintln!("Unsupported grouping Ng {}", grouping);
+                    std::process::exit(1);
+                }
+            };
+
+            if nr == 0 || nc == 0 {
+                eprintln!("Unsupported dimensions Nr {} and Nc {}", nr, nc);
+                std::process::exit(1);
+            }
+
+            let config = GeneratorConfig {
+                nr_index: nr - 1,
+                nc_index: nc - 1,
+                bandwidth,
+                grouping,
+                codebook_info: codebook,
+                feedback_type: mu as u8,
+                num_stations: stations,
+                num_soundings: soundings,
+                sounding_interval: interval,
+                report_spacing: spacing,
+                announce: !no_ndpa,
+                seed,
+                faults: FaultRates {
+                    truncation,
+                    bad_fcs,
+                    wrong_category,
+                },
+                ..Default::default()
+            };
+
+            match generate_capture(out_file, &config) {
+                Ok(summary) => println!(
+                    "Wrote {} packets: {} association responses, {} NDP Announcements, {} reports ({} truncated, {} with a bad FCS, {} with a wrong category)",
+                    summary.packets,
+                    summary.associations,
+                    summary.announcements,
+                    summary.reports,
+                    summary.truncated,
+                    summary.bad_fcs,
+                    summary.wrong_category
+                ),
+                Err(e) => {
+                    eprintln!("Capture generation failed with error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
         None => {}
     }
 }
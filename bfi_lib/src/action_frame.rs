@@ -0,0 +1,228 @@
+/** ------------------------------------------------------------
+ * Classification of frames carrying beamforming feedback.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::mac_header::FrameControl;
+use std::fmt;
+
+/**
+ * Frame type and subtype of Action No Ack frames
+ */
+pub const TYPE_MANAGEMENT: u8 = 0;
+pub const SUBTYPE_ACTION_NO_ACK: u8 = 14;
+
+/**
+ * Action categories and action codes of beamforming reports
+ */
+pub const CATEGORY_HT: u8 = 7;
+pub const HT_ACTION_COMPRESSED_BEAMFORMING: u8 = 6;
+pub const CATEGORY_VHT: u8 = 21;
+pub const VHT_ACTION_COMPRESSED_BEAMFORMING: u8 = 0;
+pub const CATEGORY_HE: u8 = 30;
+pub const HE_ACTION_COMPRESSED_BEAMFORMING_CQI: u8 = 0;
+pub const CATEGORY_EHT: u8 = 36;
+pub const EHT_ACTION_COMPRESSED_BEAMFORMING_CQI: u8 = 0;
+
+/**
+ * Length of the category and action code fields at the start of
+ * the frame body
+ */
+pub const ACTION_HEADER_LENGTH: usize = 2;
+
+/**
+ * PHY generation of a beamforming report, given by its action category
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PhyGeneration {
+    Ht,
+    Vht,
+    He,
+    Eht,
+}
+
+impl fmt::Display for PhyGeneration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhyGeneration::Ht => write!(f, "HT"),
+            PhyGeneration::Vht => write!(f, "VHT"),
+            PhyGeneration::He => write!(f, "HE"),
+            PhyGeneration::Eht => write!(f, "EHT"),
+        }
+    }
+}
+
+/**
+ * Reasons for skipping a frame that is not a beamforming report
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SkipReason {
+    NotActionNoAck { frame_type: u8, subtype: u8 },
+    UnsupportedCategory { category: u8 },
+    UnsupportedAction { category: u8, action: u8 },
+    BadFcs,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::NotActionNoAck {
+                frame_type,
+                subtype,
+            } => write!(
+                f,
+                "not Action No Ack (type {frame_type}, subtype {subtype})"
+            ),
+            SkipReason::UnsupportedCategory { category } => {
+                write!(f, "unsupported action category {category}")
+            }
+            SkipReason::UnsupportedAction { category, action } => {
+                write!(f, "unsupported action {action} in category {category}")
+            }
+            SkipReason::BadFcs => write!(f, "bad frame check sequence"),
+        }
+    }
+}
+
+/**
+ * Ensure a frame is an Action No Ack frame
+ */
+pub fn check_frame_control(frame_control: &FrameControl) -> Result<(), SkipReason> {
+    let frame_type = frame_control.frame_type().value();
+    let subtype = frame_control.subtype().value();
+
+    if frame_type != TYPE_MANAGEMENT || subtype != SUBTYPE_ACTION_NO_ACK {
+        return Err(SkipReason::NotActionNoAck {
+            frame_type,
+            subtype,
+        });
+    }
+
+    Ok(())
+}
+
+/**
+ * Category and action code at the start of an action frame body
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionHeader {
+    pub category: u8,
+    pub action: u8,
+}
+
+impl ActionHeader {
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < ACTION_HEADER_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: ACTION_HEADER_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        Ok(ActionHeader {
+            category: buf[0],
+            action: buf[1],
+        })
+    }
+
+    /**
+     * Ensure the action frame carries a compressed beamforming report,
+     * returning its PHY generation
+     */
+    pub fn check(&self) -> Result<PhyGeneration, SkipReason> {
+        match (self.category, self.action) {
+            (CATEGORY_HT, HT_ACTION_COMPRESSED_BEAMFORMING) => Ok(PhyGeneration::Ht),
+            (CATEGORY_VHT, VHT_ACTION_COMPRESSED_BEAMFORMING) => Ok(PhyGeneration::Vht),
+            (CATEGORY_HE, HE_ACTION_COMPRESSED_BEAMFORMING_CQI) => Ok(PhyGeneration::He),
+            (CATEGORY_EHT, EHT_ACTION_COMPRESSED_BEAMFORMING_CQI) => Ok(PhyGeneration::Eht),
+            (category @ (CATEGORY_HT | CATEGORY_VHT | CATEGORY_HE | CATEGORY_EHT), action) => {
+                Err(SkipReason::UnsupportedAction { category, action })
+            }
+            (category, _) => Err(SkipReason::UnsupportedCategory { category }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_control_check() {
+        // Action No Ack
+        let frame_control = FrameControl::from(0x00e0);
+        assert_eq!(check_frame_control(&frame_control), Ok(()));
+
+        // Beacon
+        let frame_control = FrameControl::from(0x0080);
+        assert_eq!(
+            check_frame_control(&frame_control),
+            Err(SkipReason::NotActionNoAck {
+                frame_type: 0,
+                subtype: 8
+            })
+        );
+
+        // NDP Announcement
+        let frame_control = FrameControl::from(0x0054);
+        assert_eq!(
+            check_frame_control(&frame_control),
+            Err(SkipReason::NotActionNoAck {
+                frame_type: 1,
+                subtype: 5
+            })
+        );
+    }
+
+    #[test]
+    fn action_header_check() {
+        let he_report = ActionHeader::from_buf(&[30, 0]).unwrap();
+        assert_eq!(he_report.check(), Ok(PhyGeneration::He));
+
+        let eht_report = ActionHeader::from_buf(&[36, 0]).unwrap();
+        assert_eq!(eht_report.check(), Ok(PhyGeneration::Eht));
+
+        let vht_report = ActionHeader::from_buf(&[21, 0]).unwrap();
+        assert_eq!(vht_report.check(), Ok(PhyGeneration::Vht));
+
+        let vht_other = ActionHeader::from_buf(&[21, 2]).unwrap();
+        assert_eq!(
+            vht_other.check(),
+            Err(SkipReason::UnsupportedAction {
+                category: 21,
+                action: 2
+            })
+        );
+
+        let ht_report = ActionHeader::from_buf(&[7, 6]).unwrap();
+        assert_eq!(ht_report.check(), Ok(PhyGeneration::Ht));
+
+        let ht_other = ActionHeader::from_buf(&[7, 5]).unwrap();
+        assert_eq!(
+            ht_other.check(),
+            Err(SkipReason::UnsupportedAction {
+                category: 7,
+                action: 5
+            })
+        );
+
+        let public_action = ActionHeader::from_buf(&[4, 0]).unwrap();
+        assert_eq!(
+            public_action.check(),
+            Err(SkipReason::UnsupportedCategory { category: 4 })
+        );
+
+        let he_other = ActionHeader::from_buf(&[30, 1]).unwrap();
+        assert_eq!(
+            he_other.check(),
+            Err(SkipReason::UnsupportedAction {
+                category: 30,
+                action: 1
+            })
+        );
+
+        assert!(matches!(
+            ActionHeader::from_buf(&[30]),
+            Err(BfaExtractionError::TruncatedFrame { .. })
+        ));
+    }
+}
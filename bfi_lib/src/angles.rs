@@ -0,0 +1,177 @@
+/** ------------------------------------------------------------
+ * Dequantization of the compressed angles to radians.
+ * ------------------------------------------------------------- */
+use crate::bfi_data::ExtractedBfiData;
+use crate::extract_bfa::{Angles, ExtractionConfig};
+use std::f64::consts::PI;
+
+/**
+ * Layout of the compressed angles of a report, which is all it takes to
+ * interpret the quantized values
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AngleLayout {
+    pub nr: usize,   // Number of rows (beamformer antennas)
+    pub nc: usize,   // Number of columns (spatial streams)
+    pub phi_bit: u8, // Bit size of the phi angles
+    pub psi_bit: u8, // Bit size of the psi angles
+}
+
+/**
+ * Phi angle in radians from its quantized value, in (0, 2π).
+ * See IEEE 802.11ax Section 9.4.1.64.
+ */
+pub fn phi_radians(quantized: u16, bits: u8) -> f64 {
+    PI * (1.0 + 2.0 * quantized as f64) / (1u32 << bits) as f64
+}
+
+/**
+ * Psi angle in radians from its quantized value, in (0, π/2)
+ */
+pub fn psi_radians(quantized: u16, bits: u8) -> f64 {
+    PI * (1.0 + 2.0 * quantized as f64) / (1u32 << (bits + 2)) as f64
+}
+
+/**
+ * Quantize a phi angle in radians to the nearest codebook value, the
+ * inverse of phi_radians
+ */
+pub fn quantize_phi(phi: f64, bits: u8) -> u16 {
+    let steps = (1u32 << bits) as f64;
+    let quantized = (phi.rem_euclid(2.0 * PI) * steps / (2.0 * PI) - 0.5).round();
+    quantized.rem_euclid(steps) as u16
+}
+
+/**
+ * Quantize a psi angle in radians to the nearest codebook value, the
+ * inverse of psi_radians
+ */
+pub fn quantize_psi(psi: f64, bits: u8) -> u16 {
+    let steps = (1u32 << bits) as f64;
+    let quantized = (psi * 2.0 * steps / PI - 0.5).round();
+    quantized.clamp(0.0, steps - 1.0) as u16
+}
+
+impl AngleLayout {
+    /**
+     * Get the angle type of every column of the extracted angles
+     */
+    pub fn angles(&self) -> Vec<Angles> {
+        ExtractionConfig::get_pattern(self.nr, self.nc)
+    }
+
+    /**
+     * Convert the quantized angles of a report (subcarrier x angle) into
+     * radians of the same shape
+     */
+    pub fn dequantize(&self, bfa_angles: &[Vec<u16>]) -> Vec<Vec<f64>> {
+        let angles = self.angles();
+        bfa_angles
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&angles)
+                    .map(|(&quantized, angle)| match angle {
+                        Angles::Phi => phi_radians(quantized, self.phi_bit),
+                        Angles::Psi => psi_radians(quantized, self.psi_bit),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /**
+     * Quantize angles in radians (subcarrier x angle) to the codebook of
+     * this layout
+     */
+    pub fn quantize(&self, bfa_radians: &[Vec<f64>]) -> Vec<Vec<u16>> {
+        let angles = self.angles();
+        bfa_radians
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&angles)
+                    .map(|(&radians, angle)| match angle {
+                        Angles::Phi => quantize_phi(radians, self.phi_bit),
+                        Angles::Psi => quantize_psi(radians, self.psi_bit),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl ExtractedBfiData {
+    /**
+     * Get the compressed angles of all reports in radians, in the same
+     * shape as bfa_angles
+     */
+    pub fn bfa_radians(&self) -> Vec<Vec<Vec<f64>>> {
+        self.bfa_angles
+            .iter()
+            .zip(&self.angle_layouts)
+            .map(|(bfa_angles, layout)| layout.dequantize(bfa_angles))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dequantize_angles() {
+        assert_eq!(phi_radians(0, 4), PI / 16.0);
+        assert_eq!(phi_radians(15, 4), 31.0 * PI / 16.0);
+        assert_eq!(psi_radians(0, 2), PI / 16.0);
+        assert_eq!(psi_radians(3, 2), 7.0 * PI / 16.0);
+        assert_eq!(phi_radians(511, 9), 1023.0 * PI / 512.0);
+        assert_eq!(psi_radians(127, 7), 255.0 * PI / 512.0);
+    }
+
+    #[test]
+    fn quantize_angles() {
+        for bits in [4, 6, 7, 9] {
+            for quantized in 0..1 << bits {
+                assert_eq!(quantize_phi(phi_radians(quantized, bits), bits), quantized);
+            }
+        }
+        for bits in [2, 4, 5, 7] {
+            for quantized in 0..1 << bits {
+                assert_eq!(quantize_psi(psi_radians(quantized, bits), bits), quantized);
+            }
+        }
+
+        // Phi wraps around, psi is clamped to its range
+        assert_eq!(quantize_phi(0.0, 4), 15);
+        assert_eq!(quantize_phi(-PI / 16.0, 4), 15);
+        assert_eq!(quantize_psi(0.0, 2), 0);
+        assert_eq!(quantize_psi(PI / 2.0, 2), 3);
+    }
+
+    #[test]
+    fn dequantize_report() {
+        // Nr 3, Nc 2: phi phi psi psi phi psi
+        let layout = AngleLayout {
+            nr: 3,
+            nc: 2,
+            phi_bit: 6,
+            psi_bit: 4,
+        };
+        let bfa_angles = vec![vec![0, 63, 0, 15, 32, 8]; 2];
+
+        let radians = layout.dequantize(&bfa_angles);
+        assert_eq!(radians.len(), 2);
+        assert_eq!(
+            radians[1],
+            vec![
+                PI / 64.0,
+                127.0 * PI / 64.0,
+                PI / 64.0,
+                31.0 * PI / 64.0,
+                65.0 * PI / 64.0,
+                17.0 * PI / 64.0,
+            ]
+        );
+    }
+}
@@ -1,14 +1,32 @@
 /** ------------------------------------------------------------
  * BFI/BFA data structs used throughout the library.
  * ------------------------------------------------------------- */
+use crate::action_frame::PhyGeneration;
+use crate::angles::AngleLayout;
+use crate::mac_header::MacAddress;
+use crate::ndpa::SoundingInfo;
+use crate::radiotap::RadiotapHeader;
+
 /**
  * Accumulated data from the packets from the pcap file
  */
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct ExtractedBfiData {
     pub timestamps: Vec<f64>,
     pub token_nums: Vec<u8>,
+    pub phy_generations: Vec<PhyGeneration>,
     pub bfa_angles: Vec<Vec<Vec<u16>>>,
+    pub angle_layouts: Vec<AngleLayout>,
+    pub delta_snr: Vec<Vec<Vec<i8>>>,
+    pub tone_indices: Vec<Vec<i16>>,
+    pub stream_snr: Vec<Vec<f32>>,
+    pub radiotap: Vec<RadiotapHeader>,
+    pub transmitters: Vec<MacAddress>,
+    pub receivers: Vec<MacAddress>,
+    pub sequence_nums: Vec<u16>,
+    pub soundings: Vec<Option<SoundingInfo>>, // NDPA of the report, if captured
+    pub fcs_valid: Vec<Option<bool>>,         // FCS validity, None if not verified
+    pub cqi_reports: Vec<CqiReport>,          // CQI-only feedback, not part of the above
 }
 
 /**
@@ -16,10 +34,87 @@ pub struct ExtractedBfiData {
  */
 impl ExtractedBfiData {
     pub fn new() -> Self {
-        Self {
-            timestamps: Vec::new(),
-            token_nums: Vec::new(),
-            bfa_angles: Vec::new(),
+        Self::default()
+    }
+
+    /**
+     * Append the data of a single packet
+     */
+    pub fn push(&mut self, packet_data: SinglePacketBfiData) {
+        let SinglePacketBfiData {
+            timestamp,
+            token_number,
+            phy_generation,
+            bfa_angles,
+            angle_layout,
+            delta_snr,
+            tone_indices,
+            stream_snr,
+            radiotap,
+            transmitter,
+            receiver,
+            sequence_number,
+            sounding,
+            fcs_valid,
+        } = packet_data;
+
+        self.timestamps.push(timestamp);
+        self.token_nums.push(token_number);
+        self.phy_generations.push(phy_generation);
+        self.bfa_angles.push(bfa_angles);
+        self.angle_layouts.push(angle_layout);
+        self.delta_snr.push(delta_snr);
+        self.tone_indices.push(tone_indices);
+        self.stream_snr.push(stream_snr);
+        self.radiotap.push(radiotap);
+        self.transmitters.push(transmitter);
+        self.receivers.push(receiver);
+        self.sequence_nums.push(sequence_number);
+        self.soundings.push(sounding);
+        self.fcs_valid.push(fcs_valid);
+    }
+
+    /**
+     * Get the beamformer of a report, which is its receiver unless the
+     * NDPA of the sounding says otherwise
+     */
+    pub fn beamformer(&self, index: usize) -> MacAddress {
+        match self.soundings.get(index).copied().flatten() {
+            Some(sounding) => sounding.beamformer,
+            None => self.receivers[index],
+        }
+    }
+
+    /**
+     * Get a copy of the reports at the given indices. CQI reports are
+     * not indexed and thus not included.
+     */
+    pub fn select(&self, indices: &[usize]) -> Self {
+        ExtractedBfiData {
+            timestamps: indices.iter().map(|&i| self.timestamps[i]).collect(),
+            token_nums: indices.iter().map(|&i| self.token_nums[i]).collect(),
+            phy_generations: indices.iter().map(|&i| self.phy_generations[i]).collect(),
+            bfa_angles: indices
+                .iter()
+                .map(|&i| self.bfa_angles[i].clone())
+                .collect(),
+            angle_layouts: indices.iter().map(|&i| self.angle_layouts[i]).collect(),
+            delta_snr: indices.iter().map(|&i| self.delta_snr[i].clone()).collect(),
+            tone_indices: indices
+                .iter()
+                .map(|&i| self.tone_indices[i].clone())
+                .collect(),
+            stream_snr: indices
+                .iter()
+                .map(|&i| self.stream_snr[i].clone())
+                .collect(),
+            radiotap: indices.iter().map(|&i| self.radiotap[i].clone()).collect(),
+            transmitters: indices.iter().map(|&i| self.transmitters[i]).collect(),
+            receivers: indices.iter().map(|&i| self.receivers[i]).collect(),
+            sequence_nums: indices.iter().map(|&i| self.sequence_nums[i]).collect(),
+            soundings: indices.iter().map(|&i| self.soundings[i]).collect(),
+            fcs_valid: indices.iter().map(|&i| self.fcs_valid[i]).collect(),
+            cqi_reports: Vec::new(),
         }
     }
 }
@@ -30,5 +125,33 @@ impl ExtractedBfiData {
 pub struct SinglePacketBfiData {
     pub timestamp: f64,
     pub token_number: u8,
+    pub phy_generation: PhyGeneration,
     pub bfa_angles: Vec<Vec<u16>>,
+    pub angle_layout: AngleLayout, // Dimensions and bit sizes of bfa_angles
+    pub delta_snr: Vec<Vec<i8>>,   // Delta SNR in dB per subcarrier and column (MU only)
+    pub tone_indices: Vec<i16>,    // Tone index of every row in bfa_angles
+    pub stream_snr: Vec<f32>,      // Average SNR in dB of every column
+    pub radiotap: RadiotapHeader,
+    pub transmitter: MacAddress,
+    pub receiver: MacAddress,
+    pub sequence_number: u16,
+    pub sounding: Option<SoundingInfo>, // NDPA announcing the sounding
+    pub fcs_valid: Option<bool>,        // All frames of the report passed the FCS check
+}
+
+/**
+ * CQI-only feedback of a single packet in the pcap
+ */
+#[derive(Debug, Clone)]
+pub struct CqiReport {
+    pub timestamp: f64,
+    pub token_number: u8,
+    pub transmitter: MacAddress,
+    pub receiver: MacAddress,
+    pub sequence_number: u16,
+    pub ru_start_index: u8,
+    pub ru_end_index: u8,
+    pub cqi: Vec<Vec<u8>>, // CQI index per 26-tone RU and stream
+    pub sounding: Option<SoundingInfo>,
+    pub fcs_valid: Option<bool>,
 }
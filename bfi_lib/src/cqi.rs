@@ -0,0 +1,86 @@
+/** ------------------------------------------------------------
+ * HE CQI report extraction (CQI-only feedback).
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::he_mimo_ctrl::HeMimoControl;
+use crate::subcarriers::check_ru_span;
+use crate::util::nibbles;
+
+/**
+ * Extract the CQI values of an HE CQI Report
+ *
+ * The report holds a 4 bit CQI index for every space-time stream of
+ * every 26-tone RU in the RU span of the MIMO control field, starting at
+ * the LSB of the first byte. See IEEE 802.11ax Table 9-91h.
+ *
+ * \returns The CQI indices per RU and stream
+ */
+pub fn extract_cqi(
+    buf: &[u8],
+    mimo_ctrl: &HeMimoControl,
+) -> Result<Vec<Vec<u8>>, BfaExtractionError> {
+    // Only the column index is meaningful for CQI feedback, the number of
+    // rows is reserved.
+    let nc = mimo_ctrl.nc_index().value() as usize + 1;
+    let num_ru = check_ru_span(
+        mimo_ctrl.bandwidth(),
+        mimo_ctrl.ru_start_index().value(),
+        mimo_ctrl.ru_end_index().value(),
+    )?;
+
+    let required = (nc * num_ru).div_ceil(2);
+    if buf.len() < required {
+        return Err(BfaExtractionError::TruncatedFrame {
+            required,
+            available: buf.len(),
+        });
+    }
+
+    let cqi: Vec<u8> = nibbles(&buf[..required]).collect();
+    Ok(cqi
+        .chunks_exact(nc)
+        .take(num_ru)
+        .map(<[u8]>::to_vec)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cqi_extraction() {
+        // Nc index 1, BW 20, CQI feedback, RU 2 to 4
+        let byte_stream: &[u8] = &[0b00001001, 0b10001000, 0b00000010, 0b11000010, 0b00001101];
+        let mimo_ctrl = HeMimoControl::from_buf(byte_stream).unwrap();
+
+        let result = extract_cqi(&[0x21, 0x43, 0x65, 0xff], &mimo_ctrl).unwrap();
+        assert_eq!(result, vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+
+        let result = extract_cqi(&[0x21, 0x43], &mimo_ctrl);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 3,
+                available: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn cqi_invalid_ru_span() {
+        // BW 20 with RU end index 12
+        let byte_stream: &[u8] = &[0b00001001, 0b10001000, 0b00000000, 0b11000110, 0b00001101];
+        let mimo_ctrl = HeMimoControl::from_buf(byte_stream).unwrap();
+
+        let result = extract_cqi(&[0x00; 16], &mimo_ctrl);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::InvalidRuSpan {
+                ru_start_index: 0,
+                ru_end_index: 12,
+                bandwidth_mhz: 20
+            })
+        ));
+    }
+}
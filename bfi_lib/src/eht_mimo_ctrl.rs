@@ -0,0 +1,153 @@
+/** ------------------------------------------------------------
+ * EHT Mimo Control Header extraction.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::he_mimo_ctrl::Bandwidth;
+use bilge::prelude::*;
+
+/**
+ * Full EHT Mimo Control fields, see IEEE 802.11be Figure 9-1004a. The
+ * reserved bits B14-B17 follow the feedback type, B38-B39 end the field.
+ */
+#[bitsize(40)]
+#[derive(FromBits, DebugBits)]
+pub struct EhtMimoControl {
+    pub nc_index: u4,
+    pub nr_index: u4,
+    pub bandwidth_index: u3,
+    pub grouping: u1,
+    pub feedback_type: u2,
+    pub reserved: u4,
+    pub remaining_feedback_segments: u3,
+    pub first_feedback_segments: u1,
+    pub partial_bw_info: u9,
+    pub dialog_token_number: u6,
+    pub codebook_info: u1,
+    pub reserved_padding: u2,
+}
+
+/**
+ * Length of the EHT MIMO Control field in bytes
+ */
+pub const EHT_MIMO_CONTROL_LENGTH: usize = 5;
+
+/**
+ * Extract EhtMimoControl from 5 bytes in buffer
+ */
+impl EhtMimoControl {
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < EHT_MIMO_CONTROL_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: EHT_MIMO_CONTROL_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        let mut bytes = [0u8; 8];
+        bytes[..EHT_MIMO_CONTROL_LENGTH].copy_from_slice(&buf[..EHT_MIMO_CONTROL_LENGTH]);
+        let value = UInt::<u64, 40>::new(u64::from_le_bytes(bytes));
+        Ok(EhtMimoControl::from(value))
+    }
+
+    /**
+     * Bandwidth of the report, indices above 320 MHz are reserved
+     */
+    pub fn bandwidth(&self) -> Result<Bandwidth, BfaExtractionError> {
+        Bandwidth::from_index(self.bandwidth_index().value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_frame::{ActionHeader, PhyGeneration, ACTION_HEADER_LENGTH};
+    use crate::extract_bfa::{extract_bfa, ExtractionConfig};
+    use crate::snr::extract_stream_snr;
+
+    #[test]
+    fn eht_mimo_ctrl_extraction() {
+        // 0011 1011 1111 1111 1110 1000 0001 1100 0011 0001 = 0x3bffe81c31
+        // EHT MIMO Control:
+        // .... .... .... .... .... .... .... .... .... 0001 = Nc Index: 2 Columns (1)
+        // .... .... .... .... .... .... .... .... 0011 .... = Nr Index: 4 Rows (3)
+        // .... .... .... .... .... .... .... .100 .... .... = BW: 320 MHz (4)
+        // .... .... .... .... .... .... .... 1... .... .... = Grouping: Ng 16 (1)
+        // .... .... .... .... .... .... ..01 .... .... .... = Feedback Type: MU (1)
+        // .... .... .... .... .... ..00 00.. .... .... .... = Reserved: 0
+        // .... .... .... .... ...0 10.. .... .... .... .... = Remaining Feedback Segments: 2
+        // .... .... .... .... ..1. .... .... .... .... .... = First Feedback Segment: 1
+        // .... .... .111 1111 11.. .... .... .... .... .... = Partial BW Info: 0x1ff
+        // ...1 1011 1... .... .... .... .... .... .... .... = Sounding Dialog Token Number: 55
+        // ..1. .... .... .... .... .... .... .... .... .... = Codebook Information: 1
+        // 00.. .... .... .... .... .... .... .... .... .... = Reserved: 0
+
+        // bytestream (little endian)
+        let byte_stream: &[u8] = &[0x31, 0x1c, 0xe8, 0xff, 0x3b];
+
+        let result = EhtMimoControl::from_buf(byte_stream).unwrap();
+        assert_eq!(result.nc_index(), UInt::<u8, 4>::new(1));
+        assert_eq!(result.nr_index(), UInt::<u8, 4>::new(3));
+        assert_eq!(result.bandwidth().unwrap(), Bandwidth::Bw320);
+        assert_eq!(result.grouping(), UInt::<u8, 1>::new(1));
+        assert_eq!(result.feedback_type(), UInt::<u8, 2>::new(1));
+        assert_eq!(result.remaining_feedback_segments(), UInt::<u8, 3>::new(2));
+        assert_eq!(result.first_feedback_segments(), UInt::<u8, 1>::new(1));
+        assert_eq!(result.partial_bw_info(), UInt::<u16, 9>::new(0x1ff));
+        assert_eq!(result.dialog_token_number(), UInt::<u8, 6>::new(55));
+        assert_eq!(result.codebook_info(), UInt::<u8, 1>::new(1));
+
+        assert!(matches!(
+            EhtMimoControl::from_buf(&byte_stream[..4]),
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 5,
+                available: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn eht_feedback_frame() {
+        // EHT Action No Ack frame with compressed beamforming feedback:
+        // 20 MHz, Nr 2, Nc 1, Ng 16, SU, codebook 0, dialog token 21
+        #[rustfmt::skip]
+        let frame: &[u8] = &[
+            0xe0, 0x00, 0x00, 0x00,             // frame control, duration
+            0x02, 0x00, 0x00, 0x00, 0x00, 0xaa, // receiver
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x01, // transmitter
+            0x02, 0x00, 0x00, 0x00, 0x00, 0xaa, // BSSID
+            0x10, 0x00,                         // sequence control
+            0x24, 0x00,                         // EHT compressed beamforming/CQI
+            0x10, 0x08, 0xa0, 0x80, 0x0a,       // EHT MIMO Control
+            0x28,                               // stream SNR: 32 dB
+            0x9a, 0xa6, 0x69, 0x9a, 0xa6, 0x69, 0x9a, 0xa6, 0x69, 0x9a,
+            0xa6, 0x69, 0x9a, 0xa6, 0x69,       // phi 10, psi 1 for 20 tones
+        ];
+
+        let body = &frame[24..];
+        let phy_generation = ActionHeader::from_buf(body).unwrap().check().unwrap();
+        assert_eq!(phy_generation, PhyGeneration::Eht);
+
+        let mimo_control = EhtMimoControl::from_buf(&body[ACTION_HEADER_LENGTH..]).unwrap();
+        assert_eq!(mimo_control.nc_index().value(), 0);
+        assert_eq!(mimo_control.nr_index().value(), 1);
+        assert_eq!(mimo_control.bandwidth().unwrap(), Bandwidth::Bw20);
+        assert_eq!(mimo_control.grouping().value(), 1);
+        assert_eq!(mimo_control.feedback_type().value(), 0);
+        assert_eq!(mimo_control.remaining_feedback_segments().value(), 0);
+        assert_eq!(mimo_control.first_feedback_segments().value(), 1);
+        assert_eq!(mimo_control.partial_bw_info().value(), 0b10);
+        assert_eq!(mimo_control.dialog_token_number().value(), 21);
+        assert_eq!(mimo_control.codebook_info().value(), 0);
+
+        // The report starts right after the 5 byte MIMO Control
+        let config = ExtractionConfig::from_eht_mimo_ctrl(&mimo_control).unwrap();
+        let report = &body[ACTION_HEADER_LENGTH + EHT_MIMO_CONTROL_LENGTH..];
+        assert_eq!(extract_stream_snr(report, config.nc).unwrap(), vec![32.0]);
+        assert_eq!(config.num_subcarrier, 20);
+        assert_eq!(config.report_length(), report.len() - config.nc);
+        assert_eq!(
+            extract_bfa(&report[config.nc..], config).unwrap(),
+            vec![vec![10, 1]; 20]
+        );
+    }
+}
@@ -10,4 +10,88 @@ pub enum BfaExtractionError {
     InsufficientBitsize { required: usize, available: usize },
     #[error("Bitsize {given} exceeds maximum handled bitsize of {allowed}")]
     InvalidBitfieldSize { given: u8, allowed: u8 },
+    #[error("Unsupported radiotap header version: {version}")]
+    UnsupportedRadiotapVersion { version: u8 },
+    #[error("Radiotap header truncated: {available} bytes available (required: {required})")]
+    TruncatedRadiotapHeader { required: usize, available: usize },
+    #[error("Unsupported capture link type {linktype} (expected radiotap, IEEE 802.11, PPI, Prism or AVS)")]
+    UnsupportedLinkType { linktype: i32 },
+    #[error("Pseudo-header truncated: {available} bytes available (required: {required})")]
+    TruncatedPseudoHeader { required: usize, available: usize },
+    #[error("Invalid pseudo-header length {length} (minimum: {minimum})")]
+    InvalidPseudoHeaderLength { length: usize, minimum: usize },
+    #[error("Frame truncated: {available} bytes available (required: {required})")]
+    TruncatedFrame { required: usize, available: usize },
+    #[error("Unsupported feedback dimensions: nr_index {nr_index}, nc_index {nc_index}")]
+    UnsupportedDimensions { nr_index: u8, nc_index: u8 },
+    #[error("Unsupported feedback type {feedback_type} with codebook info {codebook_info}")]
+    UnsupportedFeedbackType {
+        codebook_info: u8,
+        feedback_type: u8,
+    },
+    #[error("Unsupported grouping {grouping} at bandwidth {bandwidth_mhz} MHz")]
+    UnsupportedGrouping { grouping: u8, bandwidth_mhz: u32 },
+    #[error("Unsupported bandwidth index {bandwidth_index}")]
+    UnsupportedBandwidth { bandwidth_index: u8 },
+    #[error("Invalid RU span {ru_start_index} to {ru_end_index} at bandwidth {bandwidth_mhz} MHz")]
+    InvalidRuSpan {
+        ru_start_index: u8,
+        ru_end_index: u8,
+        bandwidth_mhz: u32,
+    },
+    #[error(
+        "Invalid partial bandwidth info {partial_bw_info:#x} at bandwidth {bandwidth_mhz} MHz"
+    )]
+    InvalidPartialBandwidth {
+        partial_bw_info: u16,
+        bandwidth_mhz: u32,
+    },
+    #[error("Report field {field} holds {given} values (expected: {expected})")]
+    InvalidReportShape {
+        field: &'static str,
+        expected: usize,
+        given: usize,
+    },
+    #[error("Unsupported number of stations {num_stations} (expected 1 to {max})")]
+    UnsupportedStationCount { num_stations: usize, max: usize },
+    #[error("Packet {index}: {source}")]
+    InvalidPacket {
+        index: usize,
+        #[source]
+        source: Box<BfaExtractionError>,
+    },
+    #[error("Capture error: {0}")]
+    Capture(#[from] pcap::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl BfaExtractionError {
+    /**
+     * Name of the error variant, used to group failures in reports.
+     * Errors wrapped with their packet index report the inner kind.
+     */
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InsufficientBitsize { .. } => "InsufficientBitsize",
+            Self::InvalidBitfieldSize { .. } => "InvalidBitfieldSize",
+            Self::UnsupportedRadiotapVersion { .. } => "UnsupportedRadiotapVersion",
+            Self::TruncatedRadiotapHeader { .. } => "TruncatedRadiotapHeader",
+            Self::UnsupportedLinkType { .. } => "UnsupportedLinkType",
+            Self::TruncatedPseudoHeader { .. } => "TruncatedPseudoHeader",
+            Self::InvalidPseudoHeaderLength { .. } => "InvalidPseudoHeaderLength",
+            Self::TruncatedFrame { .. } => "TruncatedFrame",
+            Self::UnsupportedDimensions { .. } => "UnsupportedDimensions",
+            Self::UnsupportedFeedbackType { .. } => "UnsupportedFeedbackType",
+            Self::UnsupportedGrouping { .. } => "UnsupportedGrouping",
+            Self::UnsupportedBandwidth { .. } => "UnsupportedBandwidth",
+            Self::InvalidRuSpan { .. } => "InvalidRuSpan",
+            Self::InvalidPartialBandwidth { .. } => "InvalidPartialBandwidth",
+            Self::InvalidReportShape { .. } => "InvalidReportShape",
+            Self::UnsupportedStationCount { .. } => "UnsupportedStationCount",
+            Self::InvalidPacket { source, .. } => source.kind(),
+            Self::Capture(_) => "Capture",
+            Self::Io(_) => "Io",
+        }
+    }
 }
@@ -1,9 +1,15 @@
 /** ------------------------------------------------------------
  * BFA extraction from bytestream payload
  * ------------------------------------------------------------- */
+use crate::angles::AngleLayout;
+use crate::eht_mimo_ctrl::EhtMimoControl;
 use crate::errors::BfaExtractionError;
-use crate::he_mimo_ctrl::Bandwidth;
-use crate::he_mimo_ctrl::HeMimoControl;
+use crate::he_mimo_ctrl::{Bandwidth, HeMimoControl};
+use crate::ht_mimo_ctrl::HtMimoControl;
+use crate::subcarriers::{
+    eht_feedback_tones, feedback_tones, ht_feedback_tones, vht_feedback_tones,
+};
+use crate::vht_mimo_ctrl::VhtMimoControl;
 
 /**
  * Extraction config contains all required parameters to extract the
@@ -13,12 +19,18 @@ use crate::he_mimo_ctrl::HeMimoControl;
 pub struct ExtractionConfig {
 	pub bitfield_pattern : Vec<u8>, // Length of bitfields per subcarrier-chunk
 	pub num_subcarrier   : usize,   // Number of subcarriers
+	pub tone_indices     : Vec<i16>, // Signed tone index of every subcarrier
+	pub nr               : usize,   // Number of rows (beamformer antennas)
+	pub nc               : usize,   // Number of columns (spatial streams)
+	pub phi_bit          : u8,      // Bit size of the phi angles
+	pub psi_bit          : u8,      // Bit size of the psi angles
 }
 
 /**
  * Compressed Feedback contains two types of angles
  */
-enum Angles {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Angles {
     Phi,
     Psi,
 }
@@ -35,57 +47,124 @@ struct CompressedAngleBitSizes {
 }
 
 /**
- * The configuration also determines the amount of angles and in which order
- * they appear in the bytestream. This array lists these orders.
+ * Maximum number of rows and columns of the feedback matrix
  */
-#[rustfmt::skip]
-const ANGLE_PATTERNS: &[&[Angles]] = &[                            // (nr_index, nc_index):
-    &[Phi, Psi],                                                   // (1, 0) | (1, 2)
-    &[Phi, Phi, Psi, Psi],                                         // (2, 0)
-    &[Phi, Phi, Psi, Psi, Phi, Psi],                               // (2, 1) | (2, 2)
-    &[Phi, Phi, Phi, Psi, Psi, Psi],                               // (3, 0)
-    &[Phi, Phi, Phi, Psi, Psi, Psi, Phi, Phi, Psi, Psi],           // (3, 1)
-    &[Phi, Phi, Phi, Psi, Psi, Psi, Phi, Phi, Psi, Psi, Phi, Psi]  // (3, 2) | (3, 3)
-];
+const MAX_DIMENSION: usize = 8;
 
 impl ExtractionConfig {
     /**
      * Get pattern in which angles appear in the compressed bitstream
+     *
+     * The angles follow the order of the Givens rotations used to compress
+     * the feedback matrix: For every column i up to min(Nc, Nr - 1), the
+     * Nr - i phi angles are followed by Nr - i psi angles. A single row
+     * needs no rotation, so the pattern is empty for Nr < 2.
+     */
+    pub fn get_pattern(nr: usize, nc: usize) -> Vec<Angles> {
+        (1..=nc.min(nr.saturating_sub(1)))
+            .flat_map(|i| {
+                let num_rotations = nr - i;
+                std::iter::repeat_n(Phi, num_rotations)
+                    .chain(std::iter::repeat_n(Psi, num_rotations))
+            })
+            .collect()
+    }
+
+    /**
+     * Get the number of rows and columns from their indices in the MIMO
+     * control field (index + 1). Only configurations with Nc <= Nr and a
+     * feedback matrix of at least 2 rows are valid.
      */
-    fn get_pattern(nr_index: u8, nc_index: u8) -> &'static [Angles] {
-        match (nr_index, nc_index) {
-            (1, 0) | (1, 2) => ANGLE_PATTERNS[0],
-            (2, 0) => ANGLE_PATTERNS[1],
-            (2, 1) | (2, 2) => ANGLE_PATTERNS[2],
-            (3, 0) => ANGLE_PATTERNS[3],
-            (3, 1) => ANGLE_PATTERNS[4],
-            (3, 2) | (3, 3) => ANGLE_PATTERNS[5],
-            _ => panic!("Invalid nr_index or nc_index"),
+    fn get_dimensions(nr_index: u8, nc_index: u8) -> Result<(usize, usize), BfaExtractionError> {
+        let nr = nr_index as usize + 1;
+        let nc = nc_index as usize + 1;
+
+        if !(2..=MAX_DIMENSION).contains(&nr) || nc > nr {
+            return Err(BfaExtractionError::UnsupportedDimensions { nr_index, nc_index });
         }
+
+        Ok((nr, nc))
     }
 
     /**
-     * Get an extraction configuration from the HeMimoControl header specification
-     * The extraction configuration specifies how to extract the compressed angles
-     * from the payload
+     * Get the signed tone indices of the subcarriers in a report, relative
+     * to the DC tone. The tones depend on the grouping bit (Ng 4 or 16) and
+     * on the RU span, as partial bandwidth feedback only covers some RUs.
+     * For more details see IEEE 802.11ax Section 27.3.2 and Table 9-91e
+     */
+    pub fn get_tone_indices(
+        bandwidth: Bandwidth,
+        grouping: u8,
+        ru_start_index: u8,
+        ru_end_index: u8,
+    ) -> Result<Vec<i16>, BfaExtractionError> {
+        let ng = match grouping {
+            0 => 4,
+            _ => 16,
+        };
+        feedback_tones(bandwidth, ng, ru_start_index, ru_end_index)
+    }
+
+    /**
+     * Length of the compressed angles in bytes, padded to a full byte
+     */
+    pub fn report_length(&self) -> usize {
+        let bits_per_subcarrier: usize = self.bitfield_pattern.iter().map(|&b| b as usize).sum();
+        (bits_per_subcarrier * self.num_subcarrier).div_ceil(8)
+    }
+
+    /**
+     * Number of angles per subcarrier
      */
-    pub fn from_he_mimo_ctrl(mimo_ctrl: &HeMimoControl) -> Self {
+    pub fn num_angles(&self) -> usize {
+        self.bitfield_pattern.len()
+    }
+
+    /**
+     * Get the layout of the extracted angles, needed to interpret them
+     * once the configuration is gone
+     */
+    pub fn angle_layout(&self) -> AngleLayout {
+        AngleLayout {
+            nr: self.nr,
+            nc: self.nc,
+            phi_bit: self.phi_bit,
+            psi_bit: self.psi_bit,
+        }
+    }
+
+    /**
+     * Get the bit sizes of the compressed angles, shared by VHT, HE and
+     * EHT. See IEEE 802.11ax Table 9-91a.
+     */
+    fn angle_bit_sizes(
+        codebook_info: u8,
+        feedback_type: u8,
+    ) -> Result<CompressedAngleBitSizes, BfaExtractionError> {
         #[rustfmt::skip]
-        let phi_psi = match (
-            mimo_ctrl.codebook_info().value(),
-            mimo_ctrl.feedback_type().value(),
-        ) {
+        let phi_psi = match (codebook_info, feedback_type) {
             (0, 0) => CompressedAngleBitSizes { phi_bit: 4, psi_bit: 2 },
             (0, 1) => CompressedAngleBitSizes { phi_bit: 7, psi_bit: 5 },
             (1, 0) => CompressedAngleBitSizes { phi_bit: 6, psi_bit: 4 },
             (1, 1) => CompressedAngleBitSizes { phi_bit: 9, psi_bit: 7 },
-            _ => panic!("Invalid codebook or feedback type"),
+            _ => return Err(BfaExtractionError::UnsupportedFeedbackType { codebook_info, feedback_type }),
         };
+        Ok(phi_psi)
+    }
 
-        let nr_index = mimo_ctrl.nr_index().value();
-        let nc_index = mimo_ctrl.nc_index().value();
-
-        let bitfield_pattern: Vec<u8> = Self::get_pattern(nr_index, nc_index)
+    /**
+     * Get an extraction configuration from the angle bit sizes, the
+     * dimensions in the MIMO control field and the tones of the report
+     */
+    fn new(
+        phi_psi: CompressedAngleBitSizes,
+        nr_index: u8,
+        nc_index: u8,
+        tone_indices: Vec<i16>,
+    ) -> Result<Self, BfaExtractionError> {
+        let (nr, nc) = Self::get_dimensions(nr_index, nc_index)?;
+
+        let bitfield_pattern: Vec<u8> = Self::get_pattern(nr, nc)
             .iter()
             .map(|pattern| match pattern {
                 Angles::Phi => phi_psi.phi_bit,
@@ -93,27 +172,139 @@ impl ExtractionConfig {
             })
             .collect();
 
-        // NOTE: based on grouping bit the number of subcarrier change
-        // for more details see IEEE 802.11ax Table 9-91a and Table 9-91e
-        let num_sub = match (
+        Ok(ExtractionConfig {
+            bitfield_pattern,
+            num_subcarrier: tone_indices.len(),
+            tone_indices,
+            nr,
+            nc,
+            phi_bit: phi_psi.phi_bit,
+            psi_bit: phi_psi.psi_bit,
+        })
+    }
+
+    /**
+     * Get an extraction configuration from the HeMimoControl header specification
+     * The extraction configuration specifies how to extract the compressed angles
+     * from the payload
+     */
+    pub fn from_he_mimo_ctrl(mimo_ctrl: &HeMimoControl) -> Result<Self, BfaExtractionError> {
+        let tone_indices = Self::get_tone_indices(
+            mimo_ctrl.bandwidth(),
             mimo_ctrl.grouping().value(),
-            mimo_ctrl.bandwidth().try_into(),
-        ) {
-            (0, Ok(Bandwidth::Bw20)) => 64,
-            (0, Ok(Bandwidth::Bw40)) => 122,
-            (0, Ok(Bandwidth::Bw80)) => 250,
-            (0, Ok(Bandwidth::Bw160)) => 500,
-            (1, Ok(Bandwidth::Bw20)) => 50,
-            (1, Ok(Bandwidth::Bw40)) => 32,
-            (1, Ok(Bandwidth::Bw80)) => 64,
-            (1, Ok(Bandwidth::Bw160)) => 160,
-            _ => panic!("Invalid grouping or BW"),
+            mimo_ctrl.ru_start_index().value(),
+            mimo_ctrl.ru_end_index().value(),
+        )?;
+
+        let phi_psi = Self::angle_bit_sizes(
+            mimo_ctrl.codebook_info().value(),
+            mimo_ctrl.feedback_type().value(),
+        )?;
+
+        Self::new(
+            phi_psi,
+            mimo_ctrl.nr_index().value(),
+            mimo_ctrl.nc_index().value(),
+            tone_indices,
+        )
+    }
+
+    /**
+     * Get an extraction configuration from the VhtMimoControl header
+     * specification. VHT uses the same angle bit sizes as HE, but
+     * feeds back a fixed set of tones per bandwidth and grouping.
+     */
+    pub fn from_vht_mimo_ctrl(mimo_ctrl: &VhtMimoControl) -> Result<Self, BfaExtractionError> {
+        let bandwidth = mimo_ctrl.bandwidth();
+        let grouping = mimo_ctrl.grouping().value();
+        let ng = match grouping {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => {
+                return Err(BfaExtractionError::UnsupportedGrouping {
+                    grouping,
+                    bandwidth_mhz: bandwidth.to_mhz(),
+                })
+            }
         };
 
-        ExtractionConfig {
-            bitfield_pattern: bitfield_pattern,
-            num_subcarrier: num_sub,
-        }
+        let phi_psi = Self::angle_bit_sizes(
+            mimo_ctrl.codebook_info().value(),
+            mimo_ctrl.feedback_type().value(),
+        )?;
+
+        Self::new(
+            phi_psi,
+            mimo_ctrl.nr_index().value(),
+            mimo_ctrl.nc_index().value(),
+            vht_feedback_tones(bandwidth, ng),
+        )
+    }
+
+    /**
+     * Get an extraction configuration from the HtMimoControl header
+     * specification. HT only has compressed SU feedback, with the angle
+     * bit sizes selected by a 2 bit codebook information.
+     * See IEEE 802.11n Section 7.3.1.29.
+     */
+    pub fn from_ht_mimo_ctrl(mimo_ctrl: &HtMimoControl) -> Result<Self, BfaExtractionError> {
+        let bandwidth = mimo_ctrl.bandwidth();
+        let grouping = mimo_ctrl.grouping().value();
+        let ng = match grouping {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => {
+                return Err(BfaExtractionError::UnsupportedGrouping {
+                    grouping,
+                    bandwidth_mhz: bandwidth.to_mhz(),
+                })
+            }
+        };
+
+        // Psi uses 1 to 4 bits, phi 2 bits more
+        let psi_bit = mimo_ctrl.codebook_info().value() + 1;
+        let phi_psi = CompressedAngleBitSizes {
+            phi_bit: psi_bit + 2,
+            psi_bit,
+        };
+
+        Self::new(
+            phi_psi,
+            mimo_ctrl.nr_index().value(),
+            mimo_ctrl.nc_index().value(),
+            ht_feedback_tones(bandwidth, ng),
+        )
+    }
+
+    /**
+     * Get an extraction configuration from the EhtMimoControl header
+     * specification. EHT replaces the RU span of HE by a bitmap of the
+     * 20 or 40 MHz subchannels covered by the report.
+     */
+    pub fn from_eht_mimo_ctrl(mimo_ctrl: &EhtMimoControl) -> Result<Self, BfaExtractionError> {
+        let ng = match mimo_ctrl.grouping().value() {
+            0 => 4,
+            _ => 16,
+        };
+        let tone_indices = eht_feedback_tones(
+            mimo_ctrl.bandwidth()?,
+            ng,
+            mimo_ctrl.partial_bw_info().value(),
+        )?;
+
+        let phi_psi = Self::angle_bit_sizes(
+            mimo_ctrl.codebook_info().value(),
+            mimo_ctrl.feedback_type().value(),
+        )?;
+
+        Self::new(
+            phi_psi,
+            mimo_ctrl.nr_index().value(),
+            mimo_ctrl.nc_index().value(),
+            tone_indices,
+        )
     }
 }
 
@@ -230,13 +421,86 @@ fn extract_bitfields(
     Ok(result)
 }
 
+/**
+ * Pack bitfields into a byte stream, the inverse of extract_bitfields
+ *
+ * Every value is written starting at its LSB, right after the previous
+ * one. The last byte is padded with zeros. Values are truncated to their
+ * bitfield size.
+ */
+pub fn pack_bitfields(bitfields: &[Vec<u16>], bitfield_pattern: &[u8]) -> Vec<u8> {
+    let mut byte_stream = Vec::new();
+    let mut bit_window = 0u32;
+    let mut window_offset = 0; // Number of bits in the window
+
+    for chunk in bitfields {
+        for (&value, &bit_length) in chunk.iter().zip(bitfield_pattern) {
+            let mask = (1u32 << bit_length) - 1;
+            bit_window |= (value as u32 & mask) << window_offset;
+            window_offset += bit_length;
+
+            while window_offset >= 8 {
+                byte_stream.push(bit_window as u8);
+                bit_window >>= 8;
+                window_offset -= 8;
+            }
+        }
+    }
+
+    if window_offset > 0 {
+        byte_stream.push(bit_window as u8);
+    }
+    byte_stream
+}
+
+/**
+ * Pack quantized angles (subcarrier x angle) into the compressed
+ * beamforming report bitstream, the inverse of extract_bfa
+ */
+pub fn pack_bfa(
+    bfa_angles: &[Vec<u16>],
+    extraction_config: &ExtractionConfig,
+) -> Result<Vec<u8>, BfaExtractionError> {
+    if bfa_angles.len() != extraction_config.num_subcarrier {
+        return Err(BfaExtractionError::InvalidReportShape {
+            field: "bfa_angles",
+            expected: extraction_config.num_subcarrier,
+            given: bfa_angles.len(),
+        });
+    }
+    if let Some(chunk) = bfa_angles
+        .iter()
+        .find(|chunk| chunk.len() != extraction_config.num_angles())
+    {
+        return Err(BfaExtractionError::InvalidReportShape {
+            field: "bfa_angles row",
+            expected: extraction_config.num_angles(),
+            given: chunk.len(),
+        });
+    }
+
+    Ok(pack_bitfields(
+        bfa_angles,
+        &extraction_config.bitfield_pattern,
+    ))
+}
+
 /**
  * Extract BFA from payload using the corresponding extraction config
+ *
+ * Unlike the raw bitfield extraction, the payload is always checked to
+ * hold enough bits, since it comes straight from a captured frame.
  */
 pub fn extract_bfa(
     bfa_payload: &[u8],
     extraction_config: ExtractionConfig,
 ) -> Result<Vec<Vec<u16>>, BfaExtractionError> {
+    sanity_check_extraction(
+        &extraction_config.bitfield_pattern,
+        extraction_config.num_subcarrier,
+        bfa_payload.len(),
+    )?;
+
     extract_bitfields(
         bfa_payload,
         extraction_config.bitfield_pattern,
@@ -249,22 +513,25 @@ mod tests {
     use super::*;
     #[test]
     fn extractioncfg_parsing_2by1() {
-        let byte_stream: &[u8] = &[0b11001000, 0b10000100, 0b00000000, 0b11000100, 0b00001101];
+        // BW 160, Ng 4, MU, RU 0 to 73 (full band)
+        let byte_stream: &[u8] = &[0b11001000, 0b10000100, 0b10000000, 0b11100100, 0b00001101];
 
-        let result_he_mimo = HeMimoControl::from_buf(byte_stream);
-        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
         let expected_bitfield_pattern = vec![7, 5]; // 7 phi, 5 psi
 
         assert_eq!(result_he_ctrl.bitfield_pattern, expected_bitfield_pattern);
         assert_eq!(result_he_ctrl.num_subcarrier, 500); //BW 160
+        assert_eq!(result_he_ctrl.report_length(), 750);
     }
 
     #[test]
     fn extractioncfg_parsing_3by2() {
-        let byte_stream: &[u8] = &[0b10010001, 0b10000000, 0b00000000, 0b11000100, 0b00001101];
+        // BW 80, Ng 4, SU, RU 0 to 36 (full band)
+        let byte_stream: &[u8] = &[0b10010001, 0b10000000, 0b00000000, 0b11010010, 0b00001101];
 
-        let result_he_mimo = HeMimoControl::from_buf(byte_stream);
-        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
         let expected_bitfield_pattern = vec![4, 4, 2, 2, 4, 2]; // 4 phi, 2 psi
 
         assert_eq!(result_he_ctrl.bitfield_pattern, expected_bitfield_pattern);
@@ -273,38 +540,252 @@ mod tests {
 
     #[test]
     fn extractioncfg_parsing_4by1() {
-        let byte_stream: &[u8] = &[0b01011000, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+        // BW 40, Ng 4, SU, RU 0 to 17 (full band)
+        let byte_stream: &[u8] = &[0b01011000, 0b10000010, 0b10000000, 0b11001000, 0b00001101];
 
-        let result_he_mimo = HeMimoControl::from_buf(byte_stream);
-        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
         let expected_bitfield_pattern = vec![6, 6, 6, 4, 4, 4]; // 6 phi, 4 psi
 
         assert_eq!(result_he_ctrl.bitfield_pattern, expected_bitfield_pattern);
         assert_eq!(result_he_ctrl.num_subcarrier, 122); //BW 40
+        assert_eq!(result_he_ctrl.report_length(), 458); // 3660 bits
     }
 
     #[test]
     fn extractioncfg_parsing_4by2() {
+        // BW 20, Ng 4, SU, RU 0 to 8 (full band)
         let byte_stream: &[u8] = &[0b00011001, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
 
-        let result_he_mimo = HeMimoControl::from_buf(byte_stream);
-        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
         let expected_bitfield_pattern = vec![6, 6, 6, 4, 4, 4, 6, 6, 4, 4]; // 6 phi, 4 psi
 
         assert_eq!(result_he_ctrl.bitfield_pattern, expected_bitfield_pattern);
         assert_eq!(result_he_ctrl.num_subcarrier, 64); //BW 20
+        assert_eq!(result_he_ctrl.tone_indices[..3], [-122, -120, -116]);
+        assert_eq!(result_he_ctrl.tone_indices[61..], [116, 120, 122]);
     }
 
     #[test]
     fn extractioncfg_parsing_4by4() {
-        let byte_stream: &[u8] = &[0b11011011, 0b10000111, 0b00000000, 0b11000100, 0b00001101];
+        // BW 160, Ng 16, MU, RU 0 to 73 (full band): 64 tones per 80 MHz
+        let byte_stream: &[u8] = &[0b11011011, 0b10000111, 0b10000000, 0b11100100, 0b00001101];
 
-        let result_he_mimo = HeMimoControl::from_buf(byte_stream);
-        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
         let expected_bitfield_pattern = vec![9, 9, 9, 7, 7, 7, 9, 9, 7, 7, 9, 7]; // 9 phi, 7 psi
 
         assert_eq!(result_he_ctrl.bitfield_pattern, expected_bitfield_pattern);
-        assert_eq!(result_he_ctrl.num_subcarrier, 160); //BW 160
+        assert_eq!(result_he_ctrl.num_subcarrier, 128); //BW 160
+    }
+
+    #[test]
+    fn extractioncfg_partial_bandwidth() {
+        // BW 80, Ng 4, RU 0 to 17 (lower 40 MHz)
+        let byte_stream: &[u8] = &[0b10001001, 0b10000000, 0b10000000, 0b11001000, 0b00001101];
+
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
+        assert_eq!(result_he_ctrl.num_subcarrier, 122); // -500 to -16
+        assert_eq!(result_he_ctrl.tone_indices[0], -500);
+        assert_eq!(result_he_ctrl.tone_indices[121], -16);
+
+        // RU end beyond the 80 MHz RUs
+        let byte_stream: &[u8] = &[0b10001001, 0b10000000, 0b00000000, 0b11111110, 0b00001101];
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::InvalidRuSpan {
+                ru_start_index: 0,
+                ru_end_index: 124,
+                bandwidth_mhz: 80
+            })
+        ));
+    }
+
+    #[test]
+    fn extractioncfg_vht() {
+        // Nc index 1, Nr index 2, BW 80, Ng 1, codebook 1, SU
+        let byte_stream: &[u8] = &[0b10010001, 0b10000100, 0b11011100];
+
+        let result_vht_mimo = VhtMimoControl::from_buf(byte_stream).unwrap();
+        let result_vht_ctrl = ExtractionConfig::from_vht_mimo_ctrl(&result_vht_mimo).unwrap();
+        let expected_bitfield_pattern = vec![6, 6, 4, 4, 6, 4]; // 6 phi, 4 psi
+
+        assert_eq!(result_vht_ctrl.bitfield_pattern, expected_bitfield_pattern);
+        assert_eq!(result_vht_ctrl.num_subcarrier, 242);
+        assert_eq!(result_vht_ctrl.tone_indices[..2], [-122, -121]);
+
+        // Grouping 3 is reserved
+        let byte_stream: &[u8] = &[0b10010001, 0b10000111, 0b11011100];
+        let result_vht_mimo = VhtMimoControl::from_buf(byte_stream).unwrap();
+        assert!(matches!(
+            ExtractionConfig::from_vht_mimo_ctrl(&result_vht_mimo),
+            Err(BfaExtractionError::UnsupportedGrouping {
+                grouping: 3,
+                bandwidth_mhz: 80
+            })
+        ));
+    }
+
+    #[test]
+    fn extractioncfg_ht() {
+        // Nc index 1, Nr index 3, BW 40, Ng 1, codebook 2
+        let byte_stream: &[u8] = &[0x1d, 0x0c, 0x78, 0x56, 0x34, 0x12];
+
+        let result_ht_mimo = HtMimoControl::from_buf(byte_stream).unwrap();
+        let result_ht_ctrl = ExtractionConfig::from_ht_mimo_ctrl(&result_ht_mimo).unwrap();
+        let expected_bitfield_pattern = vec![5, 5, 5, 3, 3, 3, 5, 5, 3, 3]; // 5 phi, 3 psi
+
+        assert_eq!(result_ht_ctrl.bitfield_pattern, expected_bitfield_pattern);
+        assert_eq!(result_ht_ctrl.num_subcarrier, 114);
+        assert_eq!(result_ht_ctrl.tone_indices[..2], [-58, -57]);
+    }
+
+    #[test]
+    fn extractioncfg_eht() {
+        // Nc index 1, Nr index 3, BW 320, Ng 16, MU, codebook 1, all 40 MHz subchannels
+        let byte_stream: &[u8] = &[0x31, 0x1c, 0xe8, 0xff, 0x3b];
+
+        let result_eht_mimo = EhtMimoControl::from_buf(byte_stream).unwrap();
+        let result_eht_ctrl = ExtractionConfig::from_eht_mimo_ctrl(&result_eht_mimo).unwrap();
+        let expected_bitfield_pattern = vec![9, 9, 9, 7, 7, 7, 9, 9, 7, 7]; // 9 phi, 7 psi
+
+        assert_eq!(result_eht_ctrl.bitfield_pattern, expected_bitfield_pattern);
+        assert_eq!(result_eht_ctrl.num_subcarrier, 256);
+        assert_eq!(result_eht_ctrl.tone_indices[..2], [-2036, -2020]);
+
+        // Only the lowest 40 MHz subchannel of a 320 MHz band
+        let byte_stream: &[u8] = &[0x31, 0x1c, 0xe8, 0x80, 0x3b];
+        let result_eht_mimo = EhtMimoControl::from_buf(byte_stream).unwrap();
+        let result_eht_ctrl = ExtractionConfig::from_eht_mimo_ctrl(&result_eht_mimo).unwrap();
+        assert_eq!(result_eht_ctrl.num_subcarrier, 32);
+        assert!(result_eht_ctrl
+            .tone_indices
+            .iter()
+            .all(|&tone| tone < -1536));
+
+        // 320 MHz only has a bitmap at 40 MHz resolution
+        let byte_stream: &[u8] = &[0x31, 0x1c, 0xa8, 0x80, 0x3b];
+        let result_eht_mimo = EhtMimoControl::from_buf(byte_stream).unwrap();
+        assert!(matches!(
+            ExtractionConfig::from_eht_mimo_ctrl(&result_eht_mimo),
+            Err(BfaExtractionError::InvalidPartialBandwidth {
+                partial_bw_info: 0x2,
+                bandwidth_mhz: 320
+            })
+        ));
+
+        // Subchannel bitmap beyond the 20 MHz band
+        let byte_stream: &[u8] = &[0x31, 0x18, 0xa8, 0x81, 0x3b];
+        let result_eht_mimo = EhtMimoControl::from_buf(byte_stream).unwrap();
+        assert!(matches!(
+            ExtractionConfig::from_eht_mimo_ctrl(&result_eht_mimo),
+            Err(BfaExtractionError::InvalidPartialBandwidth {
+                partial_bw_info: 0x6,
+                bandwidth_mhz: 20
+            })
+        ));
+    }
+
+    #[test]
+    fn extractioncfg_unsupported_dimensions() {
+        // Nr index 0 (1 row) is not a valid beamforming configuration
+        let byte_stream: &[u8] = &[0b00000000, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::UnsupportedDimensions {
+                nr_index: 0,
+                nc_index: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn extractioncfg_parsing_8by8() {
+        // Nc index 7, Nr index 7, BW 20, Ng 4, codebook 1, SU
+        let byte_stream: &[u8] = &[0b00111111, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result_he_ctrl = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
+
+        assert_eq!(result_he_ctrl.nr, 8);
+        assert_eq!(result_he_ctrl.nc, 8);
+        assert_eq!(result_he_ctrl.num_angles(), 56);
+        assert_eq!(
+            result_he_ctrl.bitfield_pattern[..14],
+            [6, 6, 6, 6, 6, 6, 6, 4, 4, 4, 4, 4, 4, 4]
+        );
+        assert_eq!(result_he_ctrl.bitfield_pattern[54..], [6, 4]);
+    }
+
+    #[test]
+    fn angle_pattern_order() {
+        assert_eq!(ExtractionConfig::get_pattern(2, 2), vec![Phi, Psi]);
+        assert_eq!(ExtractionConfig::get_pattern(1, 1), vec![]);
+        assert_eq!(ExtractionConfig::get_pattern(0, 0), vec![]);
+        assert_eq!(
+            ExtractionConfig::get_pattern(4, 2),
+            vec![Phi, Phi, Phi, Psi, Psi, Psi, Phi, Phi, Psi, Psi]
+        );
+        assert_eq!(ExtractionConfig::get_pattern(8, 2).len(), 26);
+        assert_eq!(
+            ExtractionConfig::get_pattern(5, 5),
+            ExtractionConfig::get_pattern(5, 4)
+        );
+    }
+
+    #[test]
+    fn extractioncfg_more_columns_than_rows() {
+        // Nc index 2 (3 columns), Nr index 1 (2 rows)
+        let byte_stream: &[u8] = &[0b00001010, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::UnsupportedDimensions {
+                nr_index: 1,
+                nc_index: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn extractioncfg_unsupported_feedback_type() {
+        // Feedback type 2 (CQI) carries no angles
+        let byte_stream: &[u8] = &[0b00011001, 0b10001000, 0b00000000, 0b11000100, 0b00001101];
+
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let result = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::UnsupportedFeedbackType {
+                codebook_info: 0,
+                feedback_type: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn extract_bfa_truncated_payload() {
+        let byte_stream: &[u8] = &[0b00011001, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+
+        let result_he_mimo = HeMimoControl::from_buf(byte_stream).unwrap();
+        let config = ExtractionConfig::from_he_mimo_ctrl(&result_he_mimo).unwrap();
+        let result = extract_bfa(&[0xff; 16], config);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::InsufficientBitsize {
+                required: 3200,
+                available: 128
+            })
+        ));
     }
 
     #[test]
@@ -429,6 +910,53 @@ mod tests {
     }
 
     #[test]
+    fn pack_bitfields_base() {
+        // Inverse of bitfield_extraction_base, with the unused bits of the
+        // last byte cleared
+        let bitfields: Vec<Vec<u16>> = vec![
+            vec![0b001010, 0b0011, 0b1100],
+            vec![0b110011, 0b0101, 0b1110],
+        ];
+        let expected: &[u8] = &[0b11001010, 0b11110000, 0b01011100, 0b00001110];
+
+        let result = pack_bitfields(&bitfields, &[6, 4, 4]);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn pack_bfa_round_trip() {
+        // HE 20 MHz, Nr 4, Nc 2, Ng 4, codebook 1: 64 subcarriers
+        let byte_stream: &[u8] = &[0b00011001, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+        let he_mimo_control = HeMimoControl::from_buf(byte_stream).unwrap();
+        let config = ExtractionConfig::from_he_mimo_ctrl(&he_mimo_control).unwrap();
+
+        let bfa_angles: Vec<Vec<u16>> = (0..config.num_subcarrier as u16)
+            .map(|i| {
+                config
+                    .bitfield_pattern
+                    .iter()
+                    .map(|&bits| (i * 7 + bits as u16) % (1 << bits))
+                    .collect()
+            })
+            .collect();
+
+        let payload = pack_bfa(&bfa_angles, &config).unwrap();
+        assert_eq!(payload.len(), config.report_length());
+        assert_eq!(extract_bfa(&payload, config).unwrap(), bfa_angles);
+
+        let config = ExtractionConfig::from_he_mimo_ctrl(&he_mimo_control).unwrap();
+        assert!(matches!(
+            pack_bfa(&bfa_angles[1..], &config),
+            Err(BfaExtractionError::InvalidReportShape {
+                field: "bfa_angles",
+                expected: 64,
+                given: 63
+            })
+        ));
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn capacity_error() {
         // Example payload 11001010 11110000 01011100 00111110
         // Reverse:        01010011 00001111 00111010 01111100
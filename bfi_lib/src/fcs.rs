@@ -0,0 +1,153 @@
+/** ------------------------------------------------------------
+ * Verification of the 802.11 Frame Check Sequence (FCS).
+ * ------------------------------------------------------------- */
+use crate::radiotap::RadiotapFlags;
+
+/**
+ * Length of the FCS at the end of a MAC frame
+ */
+pub const FCS_LENGTH: usize = 4;
+
+/**
+ * Reflected CRC-32 polynomial of IEEE 802.3, also used by 802.11
+ */
+const CRC32_POLYNOMIAL: u32 = 0xedb88320;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ CRC32_POLYNOMIAL,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/**
+ * CRC-32 of a byte stream, as transmitted in the FCS
+ */
+pub fn crc32(buf: &[u8]) -> u32 {
+    !buf.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/**
+ * How to handle frames with a bad FCS
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FcsPolicy {
+    #[default]
+    Drop, // Skip corrupted frames
+    Keep, // Extract corrupted frames, flagging their reports as invalid
+}
+
+/**
+ * Strip the FCS from a MAC frame and verify it
+ *
+ * The radiotap flags tell whether the capture includes the FCS and
+ * whether the driver already found it to be bad. Without flags, the FCS
+ * is only stripped if it matches, as a mismatch can not be told apart
+ * from a capture without FCS. The frame is then kept intact and its
+ * validity is left unknown.
+ *
+ * \returns The frame without FCS and whether its FCS is valid, None if
+ *          there is nothing to verify
+ */
+pub fn check_fcs(frame: &[u8], flags: Option<RadiotapFlags>) -> (&[u8], Option<bool>) {
+    let flagged_bad = flags.is_some_and(|flags| flags.bad_fcs());
+    let fcs_present = flags.is_none_or(|flags| flags.fcs_at_end());
+    if !fcs_present || frame.len() < FCS_LENGTH {
+        return (frame, flagged_bad.then_some(false));
+    }
+
+    let (body, fcs) = frame.split_at(frame.len() - FCS_LENGTH);
+    let matches = crc32(body).to_le_bytes() == fcs;
+    match flags {
+        Some(_) => (body, Some(matches && !flagged_bad)),
+        None if matches => (body, Some(true)),
+        None => (frame, None),
+    }
+}
+
+/**
+ * Validity of a report made up of several frames, invalid if any of
+ * them is
+ */
+pub fn combine_validity(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAG_FCS_AT_END: u8 = 0x10;
+    const FLAG_BAD_FCS: u8 = 0x40;
+
+    fn frame_with_fcs(body: &[u8]) -> Vec<u8> {
+        let mut frame = body.to_vec();
+        frame.extend(crc32(body).to_le_bytes());
+        frame
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn verify_fcs() {
+        let body = [0xe0, 0x00, 0x00, 0x00, 0x1e, 0x00];
+        let mut frame = frame_with_fcs(&body);
+        let flags = Some(RadiotapFlags::from(FLAG_FCS_AT_END));
+
+        assert_eq!(check_fcs(&frame, flags), (&body[..], Some(true)));
+        assert_eq!(check_fcs(&frame, None), (&body[..], Some(true)));
+
+        // Flagged bad by the driver
+        let bad = Some(RadiotapFlags::from(FLAG_FCS_AT_END | FLAG_BAD_FCS));
+        assert_eq!(check_fcs(&frame, bad), (&body[..], Some(false)));
+
+        frame[2] ^= 0x01;
+        assert_eq!(check_fcs(&frame, flags).1, Some(false));
+        assert_eq!(check_fcs(&frame, None), (&frame[..], None));
+    }
+
+    #[test]
+    fn frame_without_fcs() {
+        let body = [0xe0, 0x00, 0x00, 0x00, 0x1e, 0x00];
+
+        let flags = Some(RadiotapFlags::from(0));
+        assert_eq!(check_fcs(&body, flags), (&body[..], None));
+
+        let bad = Some(RadiotapFlags::from(FLAG_BAD_FCS));
+        assert_eq!(check_fcs(&body, bad), (&body[..], Some(false)));
+
+        // Without flags, the trailing bytes are no valid FCS and are kept
+        assert_eq!(check_fcs(&body, None), (&body[..], None));
+    }
+
+    #[test]
+    fn combined_validity() {
+        assert_eq!(combine_validity(Some(true), Some(true)), Some(true));
+        assert_eq!(combine_validity(Some(true), None), None);
+        assert_eq!(combine_validity(None, Some(false)), Some(false));
+    }
+}
@@ -0,0 +1,205 @@
+/** ------------------------------------------------------------
+ * Composition of HE compressed beamforming frames.
+ * ------------------------------------------------------------- */
+use crate::action_frame::{
+    CATEGORY_HE, HE_ACTION_COMPRESSED_BEAMFORMING_CQI, SUBTYPE_ACTION_NO_ACK, TYPE_MANAGEMENT,
+};
+use crate::errors::BfaExtractionError;
+use crate::extract_bfa::{pack_bfa, pack_bitfields, ExtractionConfig};
+use crate::fcs::crc32;
+use crate::he_mimo_ctrl::{HeMimoControl, FEEDBACK_TYPE_MU, HE_MIMO_CONTROL_LENGTH};
+use crate::mac_header::MacAddress;
+use crate::snr::{db_to_delta_snr, db_to_snr};
+use bilge::prelude::*;
+
+/**
+ * Content of an HE compressed beamforming frame
+ */
+#[derive(Debug, Clone)]
+pub struct HeFeedbackFrame {
+    pub transmitter: MacAddress, // Beamformee
+    pub receiver: MacAddress,    // Beamformer, also used as BSSID
+    pub sequence_number: u16,
+    pub mimo_control: HeMimoControl,
+    pub stream_snr: Vec<f32>,      // Average SNR in dB of every column
+    pub bfa_angles: Vec<Vec<u16>>, // Quantized angles per subcarrier
+    pub delta_snr: Vec<Vec<i8>>,   // Delta SNR in dB per subcarrier and column (MU only)
+}
+
+impl HeFeedbackFrame {
+    /**
+     * Compose the Action No Ack frame, from the MAC header up to and
+     * including the FCS
+     *
+     * The report holds the stream SNR followed by the packed angles. MU
+     * feedback is followed by the MU Exclusive Beamforming Report with the
+     * delta SNR values, which are ignored otherwise. The shapes of all
+     * fields have to match the MIMO control.
+     */
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BfaExtractionError> {
+        let config = ExtractionConfig::from_he_mimo_ctrl(&self.mimo_control)?;
+        if self.stream_snr.len() != config.nc {
+            return Err(BfaExtractionError::InvalidReportShape {
+                field: "stream_snr",
+                expected: config.nc,
+                given: self.stream_snr.len(),
+            });
+        }
+
+        // Frame control, duration, addresses and sequence control
+        let mut frame = vec![SUBTYPE_ACTION_NO_ACK << 4 | TYPE_MANAGEMENT << 2, 0, 0, 0];
+        frame.extend(self.receiver.0);
+        frame.extend(self.transmitter.0);
+        frame.extend(self.receiver.0);
+        frame.extend((self.sequence_number << 4).to_le_bytes());
+
+        frame.extend([CATEGORY_HE, HE_ACTION_COMPRESSED_BEAMFORMING_CQI]);
+        let mimo_control = u40::from(self.mimo_control).value();
+        frame.extend(&mimo_control.to_le_bytes()[..HE_MIMO_CONTROL_LENGTH]);
+
+        frame.extend(self.stream_snr.iter().map(|&db| db_to_snr(db) as u8));
+        frame.extend(pack_bfa(&self.bfa_angles, &config)?);
+
+        if self.mimo_control.feedback_type().value() == FEEDBACK_TYPE_MU {
+            frame.extend(self.pack_delta_snr(&config)?);
+        }
+
+        frame.extend(crc32(&frame).to_le_bytes());
+        Ok(frame)
+    }
+
+    /**
+     * Pack the delta SNR into 4 bit values, starting at the LSB
+     */
+    fn pack_delta_snr(&self, config: &ExtractionConfig) -> Result<Vec<u8>, BfaExtractionError> {
+        if self.delta_snr.len() != config.num_subcarrier {
+            return Err(BfaExtractionError::InvalidReportShape {
+                field: "delta_snr",
+                expected: config.num_subcarrier,
+                given: self.delta_snr.len(),
+            });
+        }
+        if let Some(row) = self.delta_snr.iter().find(|row| row.len() != config.nc) {
+            return Err(BfaExtractionError::InvalidReportShape {
+                field: "delta_snr row",
+                expected: config.nc,
+                given: row.len(),
+            });
+        }
+
+        let nibbles: Vec<Vec<u16>> = self
+            .delta_snr
+            .iter()
+            .map(|row| row.iter().map(|&db| db_to_delta_snr(db) as u16).collect())
+            .collect();
+        Ok(pack_bitfields(&nibbles, &vec![4; config.nc]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_frame::{check_frame_control, ActionHeader, ACTION_HEADER_LENGTH};
+    use crate::extract_bfa::extract_bfa;
+    use crate::fcs::check_fcs;
+    use crate::mac_header::{FrameControl, MacHeader};
+    use crate::snr::{extract_delta_snr, extract_stream_snr};
+
+    fn feedback_frame(feedback_type: u8) -> HeFeedbackFrame {
+        // HE 20 MHz, Nr 4, Nc 2, Ng 4, codebook 1: 64 subcarriers
+        let mimo_bytes = [
+            0b00011001,
+            0b10000010 | (feedback_type << 2),
+            0b00000000,
+            0b11000100,
+            0b00001101,
+        ];
+        let mimo_control = HeMimoControl::from_buf(&mimo_bytes).unwrap();
+        let config = ExtractionConfig::from_he_mimo_ctrl(&mimo_control).unwrap();
+
+        HeFeedbackFrame {
+            transmitter: MacAddress([0x02, 0, 0, 0, 0, 0x01]),
+            receiver: MacAddress([0x02, 0, 0, 0, 0, 0xaa]),
+            sequence_number: 1234,
+            mimo_control,
+            stream_snr: vec![30.25, 12.5],
+            bfa_angles: (0..config.num_subcarrier as u16)
+                .map(|i| {
+                    config
+                        .bitfield_pattern
+                        .iter()
+                        .map(|&bits| (i * 3 + 1) % (1 << bits))
+                        .collect()
+                })
+                .collect(),
+            delta_snr: (0..config.num_subcarrier as i8)
+                .map(|i| vec![i % 8, -(i % 9)])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn compose_and_parse_frame() {
+        for feedback_type in [0, FEEDBACK_TYPE_MU] {
+            let feedback = feedback_frame(feedback_type);
+            let bytes = feedback.to_bytes().unwrap();
+
+            let (frame, fcs_valid) = check_fcs(&bytes, None);
+            assert_eq!(fcs_valid, Some(true));
+            check_frame_control(&FrameControl::from_buf(frame).unwrap()).unwrap();
+
+            let mac_header = MacHeader::from_buf(frame).unwrap();
+            assert_eq!(mac_header.transmitter, feedback.transmitter);
+            assert_eq!(mac_header.receiver, feedback.receiver);
+            assert_eq!(mac_header.sequence_number(), 1234);
+
+            let body = &frame[mac_header.length()..];
+            assert!(ActionHeader::from_buf(body).unwrap().check().is_ok());
+            let mimo_control = HeMimoControl::from_buf(&body[ACTION_HEADER_LENGTH..]).unwrap();
+            assert_eq!(mimo_control.feedback_type().value(), feedback_type);
+
+            let config = ExtractionConfig::from_he_mimo_ctrl(&mimo_control).unwrap();
+            let report = &body[ACTION_HEADER_LENGTH + HE_MIMO_CONTROL_LENGTH..];
+            let stream_snr = extract_stream_snr(report, config.nc).unwrap();
+            assert_eq!(stream_snr, feedback.stream_snr);
+
+            let bfa_data = &report[config.nc..];
+            let report_length = config.report_length();
+            let num_subcarrier = config.num_subcarrier;
+            assert_eq!(extract_bfa(bfa_data, config).unwrap(), feedback.bfa_angles);
+
+            let delta_snr = &bfa_data[report_length..];
+            match feedback_type {
+                FEEDBACK_TYPE_MU => assert_eq!(
+                    extract_delta_snr(delta_snr, 2, num_subcarrier).unwrap(),
+                    feedback.delta_snr
+                ),
+                _ => assert!(delta_snr.is_empty()),
+            }
+        }
+    }
+
+    #[test]
+    fn mismatched_report_shape() {
+        let mut feedback = feedback_frame(FEEDBACK_TYPE_MU);
+        feedback.delta_snr[3].push(0);
+        assert!(matches!(
+            feedback.to_bytes(),
+            Err(BfaExtractionError::InvalidReportShape {
+                field: "delta_snr row",
+                expected: 2,
+                given: 3
+            })
+        ));
+
+        feedback.stream_snr.pop();
+        assert!(matches!(
+            feedback.to_bytes(),
+            Err(BfaExtractionError::InvalidReportShape {
+                field: "stream_snr",
+                expected: 2,
+                given: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,579 @@
+/** ------------------------------------------------------------
+ * Generation of synthetic HE beamforming feedback captures.
+ * ------------------------------------------------------------- */
+use crate::action_frame::ACTION_HEADER_LENGTH;
+use crate::errors::BfaExtractionError;
+use crate::extract_bfa::ExtractionConfig;
+use crate::fcs::{crc32, FCS_LENGTH};
+use crate::feedback_frame::HeFeedbackFrame;
+use crate::he_mimo_ctrl::{
+    Bandwidth, HeMimoControl, FEEDBACK_TYPE_MU, FEEDBACK_TYPE_SU, HE_MIMO_CONTROL_LENGTH,
+};
+use crate::mac_header::MacAddress;
+use crate::ndpa::{
+    compose_he_ndpa, FeedbackRequest, AID_DISALLOWED_SUBCHANNELS, SUBTYPE_ASSOCIATION_RESPONSE,
+};
+use crate::subcarriers::num_ru26;
+use bilge::prelude::*;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/**
+ * pcap file format constants, using the radiotap link type
+ */
+const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+const PCAP_SNAPLEN: u32 = 65535;
+const LINKTYPE_IEEE802_11_RADIOTAP: u32 = 127;
+
+/**
+ * Radiotap header with TSFT, flags (FCS at end), channel and antenna
+ * signal, all fields naturally aligned
+ */
+const RADIOTAP_LENGTH: usize = 23;
+const RADIOTAP_PRESENT: u32 = 0b10_1011;
+const RADIOTAP_FLAG_FCS_AT_END: u8 = 0x10;
+const CHANNEL_FREQ_MHZ: u16 = 5180;
+const CHANNEL_FLAGS_5GHZ_OFDM: u16 = 0x0140;
+
+/**
+ * Offset of the action category in a feedback frame, right after the
+ * MAC header
+ */
+const CATEGORY_OFFSET: usize = 24;
+
+/**
+ * Category of injected wrong category faults (public action frame)
+ */
+const WRONG_CATEGORY: u8 = 4;
+
+/**
+ * Stations get the AIDs 1 and up, below the AID of the STA Info carrying
+ * the disallowed subchannels
+ */
+const MAX_STATIONS: usize = AID_DISALLOWED_SUBCHANNELS as usize - 1;
+
+/**
+ * Fraction of the feedback frames that are corrupted on purpose. Every
+ * frame gets at most one fault, so the fractions should not sum up to
+ * more than 1.
+ */
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FaultRates {
+    pub truncation: f64,     // Report cut short, with a valid FCS
+    pub bad_fcs: f64,        // Flipped bits in the FCS
+    pub wrong_category: f64, // Public instead of HE action category
+}
+
+/**
+ * Parameters of a synthetic capture
+ */
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub nr_index: u8, // Number of rows - 1
+    pub nc_index: u8, // Number of columns - 1
+    pub bandwidth: Bandwidth,
+    pub grouping: u8,      // 0 for Ng 4, 1 for Ng 16
+    pub codebook_info: u8, // As in the MIMO Control
+    pub feedback_type: u8, // SU or MU
+    pub num_stations: usize,
+    pub num_soundings: usize,
+    pub start_timestamp: f64,   // Time of the first NDPA in seconds
+    pub sounding_interval: f64, // Time between NDPAs in seconds
+    pub report_spacing: f64,    // Time between consecutive frames of a sounding
+    pub announce: bool,         // Whether every sounding starts with an NDPA
+    pub seed: u64,              // Seed of the random angles, SNRs and faults
+    pub faults: FaultRates,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            nr_index: 3,
+            nc_index: 1,
+            bandwidth: Bandwidth::Bw80,
+            grouping: 0,
+            codebook_info: 1,
+            feedback_type: FEEDBACK_TYPE_SU,
+            num_stations: 2,
+            num_soundings: 100,
+            start_timestamp: 1_700_000_000.0,
+            sounding_interval: 0.01,
+            report_spacing: 0.0002,
+            announce: true,
+            seed: 0,
+            faults: FaultRates::default(),
+        }
+    }
+}
+
+/**
+ * Number of packets written to a synthetic capture
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GeneratorSummary {
+    pub packets: usize,
+    pub associations: usize,
+    pub announcements: usize,
+    pub reports: usize, // Feedback frames, including the corrupted ones
+    pub truncated: usize,
+    pub bad_fcs: usize,
+    pub wrong_category: usize,
+}
+
+/**
+ * Packet of a synthetic capture, including its radiotap header
+ */
+struct GeneratedPacket {
+    timestamp: f64,
+    data: Vec<u8>,
+}
+
+/**
+ * SplitMix64 generator, which keeps the captures reproducible without
+ * depending on a random number crate
+ */
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /**
+     * Uniform value in [0, 1)
+     */
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+/**
+ * Address of a beamformee, by its index starting at 0
+ */
+fn station_address(index: usize) -> MacAddress {
+    let [hi, lo] = (index as u16 + 1).to_be_bytes();
+    MacAddress([0x02, 0, 0, 0, hi, lo])
+}
+
+const BEAMFORMER: MacAddress = MacAddress([0x02, 0, 0, 0, 0, 0xaa]);
+const BROADCAST: MacAddress = MacAddress([0xff; 6]);
+
+/**
+ * Prepend the radiotap header to a frame including its FCS
+ */
+fn with_radiotap(frame: &[u8], timestamp: f64, antenna_signal: i8) -> Vec<u8> {
+    let mut packet = vec![0, 0];
+    packet.extend((RADIOTAP_LENGTH as u16).to_le_bytes());
+    packet.extend(RADIOTAP_PRESENT.to_le_bytes());
+    packet.extend(((timestamp * 1e6).round() as u64).to_le_bytes());
+    packet.extend([RADIOTAP_FLAG_FCS_AT_END, 0]); // Flags and channel padding
+    packet.extend(CHANNEL_FREQ_MHZ.to_le_bytes());
+    packet.extend(CHANNEL_FLAGS_5GHZ_OFDM.to_le_bytes());
+    packet.push(antenna_signal as u8);
+    packet.extend(frame);
+    packet
+}
+
+/**
+ * Successful association response of the beamformer to a station, which
+ * assigns the AID polled in the NDPAs
+ */
+fn association_response(station: usize) -> Vec<u8> {
+    let mut frame = vec![SUBTYPE_ASSOCIATION_RESPONSE << 4, 0, 0, 0];
+    frame.extend(station_address(station).0);
+    frame.extend(BEAMFORMER.0);
+    frame.extend(BEAMFORMER.0);
+    frame.extend([0, 0]); // Sequence control
+    frame.extend([0x11, 0x00, 0x00, 0x00]); // Capability information, status success
+    frame.extend((0xc000 | (station as u16 + 1)).to_le_bytes());
+    frame.extend(crc32(&frame).to_le_bytes());
+    frame
+}
+
+/**
+ * Replace the FCS of a frame by the one of its current content
+ */
+fn update_fcs(frame: &mut Vec<u8>) {
+    frame.truncate(frame.len() - FCS_LENGTH);
+    frame.extend(crc32(frame).to_le_bytes());
+}
+
+impl GeneratorConfig {
+    /**
+     * HE MIMO Control of the feedback of a sounding, covering the full
+     * bandwidth
+     */
+    fn mimo_control(&self, dialog_token_number: u8) -> Result<HeMimoControl, BfaExtractionError> {
+        let bandwidth_index = self.bandwidth as u8;
+        let num_ru = num_ru26(self.bandwidth);
+        if bandwidth_index > 3 || num_ru == 0 {
+            return Err(BfaExtractionError::UnsupportedBandwidth { bandwidth_index });
+        }
+        if self.nr_index > 7 || self.nc_index > 7 {
+            return Err(BfaExtractionError::UnsupportedDimensions {
+                nr_index: self.nr_index,
+                nc_index: self.nc_index,
+            });
+        }
+        if self.grouping > 1 {
+            return Err(BfaExtractionError::UnsupportedGrouping {
+                grouping: self.grouping,
+                bandwidth_mhz: self.bandwidth.to_mhz(),
+            });
+        }
+        if self.feedback_type > FEEDBACK_TYPE_MU || self.codebook_info > 1 {
+            return Err(BfaExtractionError::UnsupportedFeedbackType {
+                codebook_info: self.codebook_info,
+                feedback_type: self.feedback_type,
+            });
+        }
+
+        let mimo_control = HeMimoControl::from(u40::new(
+            self.nc_index as u64
+                | (self.nr_index as u64) << 3
+                | (bandwidth_index as u64) << 6
+                | (self.grouping as u64) << 8
+                | (self.codebook_info as u64) << 9
+                | (self.feedback_type as u64) << 10
+                | 1 << 15 // First feedback segment
+                | ((num_ru - 1) as u64) << 23
+                | ((dialog_token_number & 0x3f) as u64) << 30,
+        ));
+
+        // Reject parameters the extraction does not support
+        ExtractionConfig::from_he_mimo_ctrl(&mimo_control)?;
+        Ok(mimo_control)
+    }
+
+    /**
+     * Feedback requested from a station in the NDPA
+     */
+    fn feedback_request(&self, station: usize) -> FeedbackRequest {
+        FeedbackRequest {
+            aid: station as u16 + 1,
+            feedback_type: self.feedback_type,
+            nc_index: self.nc_index,
+            grouping: Some(self.grouping),
+            codebook_info: Some(self.codebook_info),
+            ru_span: Some((0, num_ru26(self.bandwidth) as u8 - 1)),
+            partial_bw_info: None,
+        }
+    }
+}
+
+/**
+ * Random report of a station for the given MIMO Control
+ */
+fn random_feedback(
+    rng: &mut SplitMix64,
+    station: usize,
+    sequence_number: u16,
+    mimo_control: HeMimoControl,
+) -> Result<HeFeedbackFrame, BfaExtractionError> {
+    let config = ExtractionConfig::from_he_mimo_ctrl(&mimo_control)?;
+    let num_subcarrier = config.num_subcarrier;
+    let nc = config.nc;
+
+    let stream_snr = (0..nc)
+        .map(|_| 10.0 + rng.below(120) as f32 / 4.0)
+        .collect();
+    let bfa_angles = (0..num_subcarrier)
+        .map(|_| {
+            config
+                .bitfield_pattern
+                .iter()
+                .map(|&bits| rng.below(1 << bits) as u16)
+                .collect()
+        })
+        .collect();
+    let delta_snr = match mimo_control.feedback_type().value() {
+        FEEDBACK_TYPE_MU => (0..num_subcarrier)
+            .map(|_| (0..nc).map(|_| rng.below(16) as i8 - 8).collect())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(HeFeedbackFrame {
+        transmitter: station_address(station),
+        receiver: BEAMFORMER,
+        sequence_number,
+        mimo_control,
+        stream_snr,
+        bfa_angles,
+        delta_snr,
+    })
+}
+
+/**
+ * Generate the packets of a synthetic capture
+ *
+ * Every sounding starts with an HE NDPA from the beamformer (if enabled),
+ * followed by one report per station. With NDPAs, the stations are
+ * associated first, so the reports can be linked to their STA Info.
+ * Faults are only injected into the reports, at most one per report.
+ */
+fn generate_packets(
+    config: &GeneratorConfig,
+) -> Result<(Vec<GeneratedPacket>, GeneratorSummary), BfaExtractionError> {
+    // Validate the parameters before composing any frame
+    config.mimo_control(0)?;
+    if !(1..=MAX_STATIONS).contains(&config.num_stations) {
+        return Err(BfaExtractionError::UnsupportedStationCount {
+            num_stations: config.num_stations,
+            max: MAX_STATIONS,
+        });
+    }
+
+    let mut rng = SplitMix64(config.seed);
+    let mut packets = Vec::new();
+    let mut summary = GeneratorSummary::default();
+
+    let ndpa_receiver = match config.num_stations {
+        1 => station_address(0),
+        _ => BROADCAST,
+    };
+    let sta_infos: Vec<FeedbackRequest> = (0..config.num_stations)
+        .map(|station| config.feedback_request(station))
+        .collect();
+
+    if config.announce {
+        for station in 0..config.num_stations {
+            let timestamp = config.start_timestamp
+                - (config.num_stations - station) as f64 * config.report_spacing;
+            packets.push(GeneratedPacket {
+                timestamp,
+                data: with_radiotap(&association_response(station), timestamp, -40),
+            });
+            summary.associations += 1;
+        }
+    }
+
+    for sounding in 0..config.num_soundings {
+        let dialog_token_number = (sounding % 64) as u8;
+        let mimo_control = config.mimo_control(dialog_token_number)?;
+        let start = config.start_timestamp + sounding as f64 * config.sounding_interval;
+
+        if config.announce {
+            let mut frame =
+                compose_he_ndpa(ndpa_receiver, BEAMFORMER, dialog_token_number, &sta_infos);
+            frame.extend(crc32(&frame).to_le_bytes());
+            packets.push(GeneratedPacket {
+                timestamp: start,
+                data: with_radiotap(&frame, start, -40),
+            });
+            summary.announcements += 1;
+        }
+
+        for station in 0..config.num_stations {
+            let sequence_number = (sounding % 4096) as u16;
+            let feedback = random_feedback(&mut rng, station, sequence_number, mimo_control)?;
+            let mut frame = feedback.to_bytes()?;
+
+            let fault = rng.uniform();
+            let faults = config.faults;
+            if fault < faults.truncation {
+                // Keep the stream SNR and half of the angles
+                let report_start = CATEGORY_OFFSET
+                    + ACTION_HEADER_LENGTH
+                    + HE_MIMO_CONTROL_LENGTH
+                    + feedback.stream_snr.len();
+                let report_length = frame.len() - FCS_LENGTH - report_start;
+                frame.truncate(report_start + report_length / 2 + FCS_LENGTH);
+                update_fcs(&mut frame);
+                summary.truncated += 1;
+            } else if fault < faults.truncation + faults.bad_fcs {
+                let fcs_start = frame.len() - FCS_LENGTH;
+                frame[fcs_start] ^= 0xff;
+                summary.bad_fcs += 1;
+            } else if fault < faults.truncation + faults.bad_fcs + faults.wrong_category {
+                frame[CATEGORY_OFFSET] = WRONG_CATEGORY;
+                update_fcs(&mut frame);
+                summary.wrong_category += 1;
+            }
+
+            let timestamp = start + (station + 1) as f64 * config.report_spacing;
+            let antenna_signal = -30 - rng.below(50) as i8;
+            packets.push(GeneratedPacket {
+                timestamp,
+                data: with_radiotap(&frame, timestamp, antenna_signal),
+            });
+            summary.reports += 1;
+        }
+    }
+
+    summary.packets = packets.len();
+    Ok((packets, summary))
+}
+
+/**
+ * Write a synthetic capture of HE beamforming feedback
+ *
+ * \param capture_path Path of the pcap file to write
+ * \param config       Parameters of the capture
+ *
+ * The frames follow a radiotap header and carry their FCS. The same
+ * config and seed always result in the same capture.
+ */
+pub fn generate_capture(
+    capture_path: PathBuf,
+    config: &GeneratorConfig,
+) -> Result<GeneratorSummary, BfaExtractionError> {
+    let (packets, summary) = generate_packets(config)?;
+
+    let mut writer = BufWriter::new(File::create(capture_path)?);
+    writer.write_all(&PCAP_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?; // Version 2.4
+    writer.write_all(&4u16.to_le_bytes())?;
+    writer.write_all(&[0; 8])?; // Time zone and timestamp accuracy
+    writer.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+    writer.write_all(&LINKTYPE_IEEE802_11_RADIOTAP.to_le_bytes())?;
+
+    for packet in packets {
+        let seconds = packet.timestamp.floor();
+        let microseconds = ((packet.timestamp - seconds) * 1e6).round().min(999_999.0);
+        writer.write_all(&(seconds as u32).to_le_bytes())?;
+        writer.write_all(&(microseconds as u32).to_le_bytes())?;
+        writer.write_all(&(packet.data.len() as u32).to_le_bytes())?; // Captured length
+        writer.write_all(&(packet.data.len() as u32).to_le_bytes())?; // Original length
+        writer.write_all(&packet.data)?;
+    }
+
+    writer.flush()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_frame::SkipReason;
+    use crate::{extract_from_capture_with_options, ExtractionMode, ExtractionOptions};
+
+    fn temp_capture(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bfi_{}_{}.pcap", name, std::process::id()))
+    }
+
+    #[test]
+    fn reproducible_packets() {
+        let config = GeneratorConfig {
+            bandwidth: Bandwidth::Bw20,
+            num_soundings: 3,
+            seed: 42,
+            ..Default::default()
+        };
+        let (first, summary) = generate_packets(&config).unwrap();
+        let (second, _) = generate_packets(&config).unwrap();
+        assert!(first.iter().zip(&second).all(|(a, b)| a.data == b.data));
+        assert_eq!(
+            summary,
+            GeneratorSummary {
+                packets: 11,
+                associations: 2,
+                announcements: 3,
+                reports: 6,
+                ..Default::default()
+            }
+        );
+
+        let (other, _) = generate_packets(&GeneratorConfig { seed: 43, ..config }).unwrap();
+        assert!(first[3].data != other[3].data);
+    }
+
+    #[test]
+    fn unsupported_parameters() {
+        let config = GeneratorConfig {
+            bandwidth: Bandwidth::Bw320,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_packets(&config),
+            Err(BfaExtractionError::UnsupportedBandwidth { bandwidth_index: 4 })
+        ));
+
+        let config = GeneratorConfig {
+            feedback_type: 2,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_packets(&config),
+            Err(BfaExtractionError::UnsupportedFeedbackType { .. })
+        ));
+
+        // AID 2047 is reserved, larger ones do not fit the STA Info
+        for num_stations in [0, 2047, 3000] {
+            let config = GeneratorConfig {
+                num_stations,
+                ..Default::default()
+            };
+            assert!(matches!(
+                generate_packets(&config),
+                Err(BfaExtractionError::UnsupportedStationCount { max: 2046, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn extract_generated_capture() {
+        let path = temp_capture("generated");
+        let config = GeneratorConfig {
+            nr_index: 2,
+            nc_index: 1,
+            bandwidth: Bandwidth::Bw40,
+            feedback_type: FEEDBACK_TYPE_MU,
+            num_stations: 3,
+            num_soundings: 40,
+            seed: 7,
+            faults: FaultRates {
+                truncation: 0.05,
+                bad_fcs: 0.05,
+                wrong_category: 0.05,
+            },
+            ..Default::default()
+        };
+        let summary = generate_capture(path.clone(), &config).unwrap();
+        assert!(summary.truncated > 0 && summary.bad_fcs > 0 && summary.wrong_category > 0);
+
+        let options = ExtractionOptions {
+            mode: ExtractionMode::Lenient,
+            ..Default::default()
+        };
+        let (data, report) = extract_from_capture_with_options(path.clone(), &options).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let valid = summary.reports - summary.truncated - summary.bad_fcs - summary.wrong_category;
+        assert_eq!(report.total_packets, summary.packets);
+        assert_eq!(report.ndp_announcements, 40);
+        assert_eq!(report.extracted, valid);
+        assert_eq!(report.num_failed(), summary.truncated);
+        assert_eq!(report.skipped_frames[&SkipReason::BadFcs], summary.bad_fcs);
+        assert_eq!(
+            report.skipped_frames[&SkipReason::UnsupportedCategory { category: 4 }],
+            summary.wrong_category
+        );
+        assert_eq!(report.mismatched_reports, 0);
+        assert!(data
+            .soundings
+            .iter()
+            .all(|sounding| sounding.is_some_and(|sounding| sounding.requested.is_some())));
+
+        // Nr 3, Nc 2 at 40 MHz with Ng 4
+        assert!(data
+            .angle_layouts
+            .iter()
+            .all(|layout| layout.nr == 3 && layout.nc == 2));
+        assert!(data.tone_indices.iter().all(|tones| tones.len() == 122));
+        assert!(data
+            .delta_snr
+            .iter()
+            .all(|delta_snr| delta_snr.len() == 122));
+        assert_eq!(report.sounding_stats[&BEAMFORMER].soundings, 40);
+    }
+}
@@ -1,24 +1,50 @@
 /** ------------------------------------------------------------
  * HE Mimo Control Header extraction.
  * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
 use bilge::prelude::*;
 
 /**
- * Bandwidth enum corresponding to index order in HE MIMO Control field
+ * Bandwidth enum corresponding to index order in the MIMO Control fields
  */
-#[bitsize(2)]
-#[derive(FromBits, Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Bandwidth {
     Bw20,
     Bw40,
     Bw80,
     Bw160,
+    Bw320, // EHT only
 }
 
 /**
  * Bandwidth conversion functions
  */
 impl Bandwidth {
+    const ALL: [Bandwidth; 5] = [
+        Bandwidth::Bw20,
+        Bandwidth::Bw40,
+        Bandwidth::Bw80,
+        Bandwidth::Bw160,
+        Bandwidth::Bw320,
+    ];
+
+    /**
+     * Get the bandwidth from its index in a MIMO Control field
+     */
+    pub fn from_index(bandwidth_index: u8) -> Result<Self, BfaExtractionError> {
+        Self::ALL
+            .get(bandwidth_index as usize)
+            .copied()
+            .ok_or(BfaExtractionError::UnsupportedBandwidth { bandwidth_index })
+    }
+
+    /**
+     * Get the bandwidth from a 2 bit index, which is always valid
+     */
+    pub(crate) fn from_u2(bandwidth_index: u2) -> Self {
+        Self::ALL[bandwidth_index.value() as usize]
+    }
+
     pub fn to_mhz(self) -> u32 {
         // Left shift is equal to taking power of 2
         (2 << (self as u32)) * 10
@@ -33,11 +59,11 @@ impl Bandwidth {
  * Full HE Mimo Control fields
  */
 #[bitsize(40)]
-#[derive(FromBits, DebugBits)]
+#[derive(FromBits, DebugBits, Clone, Copy)]
 pub struct HeMimoControl {
     pub nc_index: u3,
     pub nr_index: u3,
-    pub bandwidth: Bandwidth,
+    pub bandwidth_index: u2,
     pub grouping: u1,
     pub codebook_info: u1,
     pub feedback_type: u2,
@@ -49,11 +75,30 @@ pub struct HeMimoControl {
     pub reserved_padding: u4,
 }
 
+/**
+ * Values of the feedback type field
+ */
+pub const FEEDBACK_TYPE_SU: u8 = 0;
+pub const FEEDBACK_TYPE_MU: u8 = 1;
+pub const FEEDBACK_TYPE_CQI: u8 = 2;
+
+/**
+ * Length of the HE MIMO Control field in bytes
+ */
+pub const HE_MIMO_CONTROL_LENGTH: usize = 5;
+
 /**
  * Extract HeMimoControl from 5 bytes in buffer
  */
 impl HeMimoControl {
-    pub fn from_buf(buf: &[u8]) -> Self {
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < HE_MIMO_CONTROL_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: HE_MIMO_CONTROL_LENGTH,
+                available: buf.len(),
+            });
+        }
+
         let test: UInt<u64, 40> = UInt::<u64, 40>::new(
             (buf[0] as u64)
                 | ((buf[1] as u64) << 8)
@@ -61,7 +106,11 @@ impl HeMimoControl {
                 | ((buf[3] as u64) << 24)
                 | ((buf[4] as u64) << 32),
         );
-        HeMimoControl::from(test)
+        Ok(HeMimoControl::from(test))
+    }
+
+    pub fn bandwidth(&self) -> Bandwidth {
+        Bandwidth::from_u2(self.bandwidth_index())
     }
 }
 
@@ -89,7 +138,7 @@ mod tests {
         // bytestream (little endian)
         let byte_stream: &[u8] = &[0b00011001, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
 
-        let result = HeMimoControl::from_buf(byte_stream);
+        let result = HeMimoControl::from_buf(byte_stream).unwrap();
         assert_eq!(result.nc_index(), UInt::<u8, 3>::new(1));
         assert_eq!(result.nr_index(), UInt::<u8, 3>::new(3));
         assert_eq!(result.bandwidth(), Bandwidth::Bw20);
@@ -110,6 +159,7 @@ mod tests {
         assert_eq!(Bandwidth::Bw40.to_hz(), 40_000_000);
         assert_eq!(Bandwidth::Bw80.to_hz(), 80_000_000);
         assert_eq!(Bandwidth::Bw160.to_hz(), 160_000_000);
+        assert_eq!(Bandwidth::Bw320.to_hz(), 320_000_000);
     }
 
     #[test]
@@ -118,5 +168,16 @@ mod tests {
         assert_eq!(Bandwidth::Bw40.to_mhz(), 40);
         assert_eq!(Bandwidth::Bw80.to_mhz(), 80);
         assert_eq!(Bandwidth::Bw160.to_mhz(), 160);
+        assert_eq!(Bandwidth::Bw320.to_mhz(), 320);
+    }
+
+    #[test]
+    fn bandwidth_from_index() {
+        assert_eq!(Bandwidth::from_index(1).unwrap(), Bandwidth::Bw40);
+        assert_eq!(Bandwidth::from_index(4).unwrap(), Bandwidth::Bw320);
+        assert!(matches!(
+            Bandwidth::from_index(5),
+            Err(BfaExtractionError::UnsupportedBandwidth { bandwidth_index: 5 })
+        ));
     }
 }
@@ -0,0 +1,99 @@
+/** ------------------------------------------------------------
+ * HT Mimo Control Header extraction.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::he_mimo_ctrl::Bandwidth;
+use bilge::prelude::*;
+
+/**
+ * Full HT Mimo Control fields, including the Sounding Timestamp that
+ * follows the 2 byte control bits in every HT MIMO Control field.
+ * HT has neither a sounding dialog token nor a first segment flag.
+ */
+#[bitsize(48)]
+#[derive(FromBits, DebugBits)]
+pub struct HtMimoControl {
+    pub nc_index: u2,
+    pub nr_index: u2,
+    pub channel_width: u1,
+    pub grouping: u2,
+    pub coefficient_size: u2,
+    pub codebook_info: u2,
+    pub remaining_matrix_segments: u3,
+    pub reserved: u2,
+    pub sounding_timestamp: u32,
+}
+
+/**
+ * Length of the HT MIMO Control field in bytes
+ */
+pub const HT_MIMO_CONTROL_LENGTH: usize = 6;
+
+/**
+ * Extract HtMimoControl from 6 bytes in buffer
+ */
+impl HtMimoControl {
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < HT_MIMO_CONTROL_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: HT_MIMO_CONTROL_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        let mut bytes = [0u8; 8];
+        bytes[..HT_MIMO_CONTROL_LENGTH].copy_from_slice(&buf[..HT_MIMO_CONTROL_LENGTH]);
+        let value = UInt::<u64, 48>::new(u64::from_le_bytes(bytes));
+        Ok(HtMimoControl::from(value))
+    }
+
+    /**
+     * Bandwidth of the report, HT only knows 20 and 40 MHz
+     */
+    pub fn bandwidth(&self) -> Bandwidth {
+        match self.channel_width().value() {
+            0 => Bandwidth::Bw20,
+            _ => Bandwidth::Bw40,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ht_mimo_ctrl_extraction() {
+        // 0000 1100 0001 1101 = 0x0c1d
+        // HT MIMO Control:
+        // .... .... .... ..01 = Nc Index: 2 Columns (1)
+        // .... .... .... 11.. = Nr Index: 4 Rows (3)
+        // .... .... ...1 .... = MIMO Control Channel Width: 40 MHz (1)
+        // .... .... .00. .... = Grouping: No Grouping (0)
+        // .... ...0 0... .... = Coefficient Size: 4 bits (0)
+        // .... .10. .... .... = Codebook Information: 2
+        // ..00 1... .... .... = Remaining Matrix Segment: 1
+        // 00.. .... .... .... = Reserved: 0
+        // Sounding Timestamp: 0x12345678
+
+        // bytestream (little endian)
+        let byte_stream: &[u8] = &[0x1d, 0x0c, 0x78, 0x56, 0x34, 0x12];
+
+        let result = HtMimoControl::from_buf(byte_stream).unwrap();
+        assert_eq!(result.nc_index(), UInt::<u8, 2>::new(1));
+        assert_eq!(result.nr_index(), UInt::<u8, 2>::new(3));
+        assert_eq!(result.bandwidth(), Bandwidth::Bw40);
+        assert_eq!(result.grouping(), UInt::<u8, 2>::new(0));
+        assert_eq!(result.codebook_info(), UInt::<u8, 2>::new(2));
+        assert_eq!(result.remaining_matrix_segments(), UInt::<u8, 3>::new(1));
+        assert_eq!(result.sounding_timestamp(), 0x12345678);
+
+        assert!(matches!(
+            HtMimoControl::from_buf(&byte_stream[..5]),
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 6,
+                available: 5
+            })
+        ));
+    }
+}
@@ -3,52 +3,346 @@
 /** ------------------------------------------------------------
  * Public library API
  * ------------------------------------------------------------- */
+mod action_frame;
+mod angles;
+mod cqi;
+mod eht_mimo_ctrl;
 mod errors;
 mod extract_bfa;
+mod fcs;
+mod feedback_frame;
+mod generator;
 mod he_mimo_ctrl;
+mod ht_mimo_ctrl;
+mod link_type;
+mod mac_header;
+mod mimo_ctrl;
+mod ndpa;
 mod persistence;
+mod radiotap;
+mod reassembly;
+mod report;
+mod sequence;
+mod session;
+mod snr;
+mod subcarriers;
+#[cfg(test)]
+mod test_util;
 mod util;
+mod v_matrix;
+mod vht_mimo_ctrl;
 
-use extract_bfa::{extract_bfa, ExtractionConfig};
-use he_mimo_ctrl::HeMimoControl;
+use action_frame::{check_frame_control, ActionHeader, ACTION_HEADER_LENGTH};
+use cqi::extract_cqi;
+use extract_bfa::extract_bfa;
+use fcs::check_fcs;
+use he_mimo_ctrl::{FEEDBACK_TYPE_CQI, FEEDBACK_TYPE_MU};
+use link_type::LinkType;
+use mac_header::{FrameControl, MacHeader};
+use mimo_ctrl::MimoControl;
+use ndpa::{
+    is_association_response, is_ndpa, Association, Ndpa, NdpaTracker, SUBTYPE_NDPA, TYPE_CONTROL,
+};
 use pcap::{Capture, Packet};
+use reassembly::SegmentReassembler;
+use snr::{extract_delta_snr, extract_stream_snr};
 use std::path::PathBuf;
+use util::align_up;
 
 // Public re-export
 pub mod bfi_data;
-pub use crate::bfi_data::{ExtractedBfiData, SinglePacketBfiData};
+pub use crate::action_frame::{PhyGeneration, SkipReason};
+pub use crate::angles::{phi_radians, psi_radians, quantize_phi, quantize_psi, AngleLayout};
+pub use crate::bfi_data::{CqiReport, ExtractedBfiData, SinglePacketBfiData};
+pub use crate::eht_mimo_ctrl::EhtMimoControl;
+pub use crate::errors::BfaExtractionError;
+pub use crate::extract_bfa::{pack_bfa, Angles, ExtractionConfig};
+pub use crate::fcs::FcsPolicy;
+pub use crate::feedback_frame::HeFeedbackFrame;
+pub use crate::generator::{generate_capture, FaultRates, GeneratorConfig, GeneratorSummary};
+pub use crate::he_mimo_ctrl::{Bandwidth, HeMimoControl};
+pub use crate::ht_mimo_ctrl::HtMimoControl;
+pub use crate::mac_header::MacAddress;
+pub use crate::ndpa::{FeedbackRequest, SoundingInfo, DEFAULT_SOUNDING_WINDOW};
+pub use crate::persistence::{cqi_parquet_path, ParquetOptions};
+pub use crate::radiotap::{RadiotapFlags, RadiotapHeader};
+pub use crate::report::{ExtractionReport, FailureStats};
+pub use crate::sequence::{SequenceStats, SoundingSequence};
+pub use crate::session::{SoundingSession, DEFAULT_SESSION_WINDOW};
+pub use crate::v_matrix::{compress_v, decompose_v, reconstruct_v};
+pub use crate::vht_mimo_ctrl::VhtMimoControl;
 
 /**
- * Extract data from a single packet
+ * Outcome of processing a single packet
+ */
+enum PacketOutcome {
+    Report(SinglePacketBfiData),
+    Cqi(CqiReport),
+    Segment,      // Segment of a report still waiting for the other segments
+    Announcement, // NDP Announcement, kept to link the following reports
+    Association,  // (Re)association response, assigning the AID of a station
+    Skipped(SkipReason),
+}
+
+/**
+ * Beamforming feedback frame (or segment) before decoding its report
+ */
+struct FeedbackFrame {
+    timestamp: f64,
+    radiotap: RadiotapHeader,
+    mac_header: MacHeader,
+    mimo_control: MimoControl,
+    payload: Vec<u8>,        // Report bytes between MIMO control and FCS
+    fcs_valid: Option<bool>, // None if the FCS could not be verified
+}
+
+/**
+ * Frame relevant to the extraction
  */
-fn extract_from_packet(packet: &Packet) -> SinglePacketBfiData {
-    const MIMO_CTRL_HEADER_OFFSET: usize = 26;
-    const BFA_HEADER_OFFSET: usize = 7;
-    const FCS_LENGTH: usize = 4;
+enum CapturedFrame {
+    Feedback(FeedbackFrame),
+    Ndpa(Ndpa),
+    Association(Association),
+}
 
+/**
+ * Extract the feedback frame, NDP Announcement or association response
+ * from a single packet
+ *
+ * Packets carrying neither a compressed beamforming report nor a sounding
+ * announcement or association are skipped with the corresponding reason,
+ * malformed ones result in an error. Frames with a bad FCS are skipped as
+ * well, unless the policy keeps them.
+ */
+fn extract_frame(
+    packet: &Packet,
+    link_type: LinkType,
+    fcs_policy: FcsPolicy,
+) -> Result<Result<CapturedFrame, SkipReason>, BfaExtractionError> {
     // Extract the timestamp from the pcap packet
     let timestamp = packet.header.ts;
     let timestamp_secs = timestamp.tv_sec as f64 + timestamp.tv_usec as f64 * 1e-6;
 
-    let header_length = u16::from_le_bytes([packet.data[2], packet.data[3]]) as usize;
-    let mimo_ctrl_start = header_length + MIMO_CTRL_HEADER_OFFSET;
+    let radiotap = link_type.parse_header(packet)?;
+    let (frame, fcs_valid) = check_fcs(&packet[radiotap.length..], radiotap.flags);
+    if fcs_valid == Some(false) && fcs_policy == FcsPolicy::Drop {
+        return Ok(Err(SkipReason::BadFcs));
+    }
+
+    // Check the frame type first, other frames may not even have a full
+    // management header.
+    let frame_control = FrameControl::from_buf(frame)?;
+    if is_ndpa(&frame_control) {
+        return Ok(match Ndpa::from_buf(frame, timestamp_secs)? {
+            Some(ndpa) => Ok(CapturedFrame::Ndpa(ndpa)),
+            None => Err(SkipReason::NotActionNoAck {
+                frame_type: TYPE_CONTROL,
+                subtype: SUBTYPE_NDPA,
+            }),
+        });
+    }
+    if is_association_response(&frame_control) {
+        return Ok(match Association::from_buf(frame)? {
+            Some(association) => Ok(CapturedFrame::Association(association)),
+            None => Err(SkipReason::NotActionNoAck {
+                frame_type: frame_control.frame_type().value(),
+                subtype: frame_control.subtype().value(),
+            }),
+        });
+    }
+    if let Err(reason) = check_frame_control(&frame_control) {
+        return Ok(Err(reason));
+    }
+
+    let mac_header = MacHeader::from_buf(frame)?;
+
+    // The frame body follows the MAC header (including HT Control in +HTC
+    // frames). Some drivers pad the header to a 32 bit boundary and flag
+    // that in the radiotap header.
+    let mut body_offset = mac_header.length();
+    if radiotap.flags.is_some_and(|flags| flags.data_pad()) {
+        body_offset = align_up(body_offset, 4);
+    }
 
-    let mimo_control = HeMimoControl::from_buf(&packet[mimo_ctrl_start..]);
+    let body = frame.get(body_offset..).unwrap_or_default();
+    let action_header = ActionHeader::from_buf(body)?;
+    let phy_generation = match action_header.check() {
+        Ok(phy_generation) => phy_generation,
+        Err(reason) => return Ok(Err(reason)),
+    };
 
-    // NOTE: BFA data starts after mimo_control (5 bytes) and SNR (2 bytes)
-    // They last until before the last four bytes (Frame Check Sequence)
-    let bfa_start = mimo_ctrl_start + BFA_HEADER_OFFSET;
-    let bfa_end = packet.len() - FCS_LENGTH;
+    let mimo_ctrl_start = body_offset + ACTION_HEADER_LENGTH;
 
-    // Extract the binary data of the BFA angles
-    let bfa_data = &packet[bfa_start..bfa_end];
-    let bfa_angles = extract_bfa(bfa_data, ExtractionConfig::from_he_mimo_ctrl(&mimo_control))
-        .expect("BFA extraction failed");
+    let mimo_control = MimoControl::from_buf(phy_generation, &frame[mimo_ctrl_start..])?;
 
-    SinglePacketBfiData {
+    // NOTE: The report (or report segment) follows the MIMO control and
+    // lasts until the end of the frame without FCS
+    let report_start = mimo_ctrl_start + mimo_control.length();
+    let payload = frame.get(report_start..).unwrap_or_default();
+
+    Ok(Ok(CapturedFrame::Feedback(FeedbackFrame {
         timestamp: timestamp_secs,
-        token_number: u8::from(mimo_control.dialog_token_number()),
-        bfa_angles: bfa_angles,
+        radiotap,
+        mac_header,
+        mimo_control,
+        payload: payload.to_vec(),
+        fcs_valid,
+    })))
+}
+
+/**
+ * Decode the report of a complete feedback frame
+ *
+ * CQI-only feedback is returned separately from beamforming reports.
+ * Reports are linked to the NDP Announcement of their sounding.
+ */
+fn decode_feedback(
+    frame: FeedbackFrame,
+    ndpas: &NdpaTracker,
+) -> Result<PacketOutcome, BfaExtractionError> {
+    let FeedbackFrame {
+        timestamp,
+        radiotap,
+        mac_header,
+        mimo_control,
+        payload,
+        fcs_valid,
+    } = frame;
+
+    // The beamformee sends the report to the beamformer
+    let sounding = ndpas.link(
+        timestamp,
+        mac_header.transmitter,
+        mac_header.receiver,
+        &mimo_control,
+    );
+
+    // CQI-only feedback carries no angles, just the HE CQI Report
+    if let MimoControl::He(he_mimo_control) = &mimo_control {
+        if he_mimo_control.feedback_type().value() == FEEDBACK_TYPE_CQI {
+            return Ok(PacketOutcome::Cqi(CqiReport {
+                timestamp,
+                token_number: mimo_control.dialog_token_number(),
+                transmitter: mac_header.transmitter,
+                receiver: mac_header.receiver,
+                sequence_number: mac_header.sequence_number(),
+                ru_start_index: he_mimo_control.ru_start_index().value(),
+                ru_end_index: he_mimo_control.ru_end_index().value(),
+                cqi: extract_cqi(&payload, he_mimo_control)?,
+                sounding,
+                fcs_valid,
+            }));
+        }
+    }
+
+    let extraction_config = mimo_control.extraction_config()?;
+
+    // NOTE: The report starts with one average SNR byte per column,
+    // followed by the BFA data.
+    let stream_snr = extract_stream_snr(&payload, extraction_config.nc)?;
+    let bfa_data = &payload[extraction_config.nc..];
+
+    // HE and EHT MU feedback append an MU Exclusive Beamforming Report with
+    // delta SNR values to the byte-aligned compressed angles. The VHT one
+    // uses a different set of subcarriers and is not decoded, HT has none.
+    let phy_generation = mimo_control.phy_generation();
+    let delta_snr = match mimo_control.feedback_type() {
+        FEEDBACK_TYPE_MU if matches!(phy_generation, PhyGeneration::He | PhyGeneration::Eht) => {
+            let delta_snr_start = extraction_config.report_length().min(bfa_data.len());
+            extract_delta_snr(
+                &bfa_data[delta_snr_start..],
+                extraction_config.nc,
+                extraction_config.num_subcarrier,
+            )?
+        }
+        _ => Vec::new(),
+    };
+
+    // Extract the binary data of the BFA angles
+    let tone_indices = extraction_config.tone_indices.clone();
+    let angle_layout = extraction_config.angle_layout();
+    let bfa_angles = extract_bfa(bfa_data, extraction_config)?;
+
+    Ok(PacketOutcome::Report(SinglePacketBfiData {
+        timestamp,
+        token_number: mimo_control.dialog_token_number(),
+        phy_generation,
+        bfa_angles,
+        angle_layout,
+        delta_snr,
+        tone_indices,
+        stream_snr,
+        radiotap,
+        transmitter: mac_header.transmitter,
+        receiver: mac_header.receiver,
+        sequence_number: mac_header.sequence_number(),
+        sounding,
+        fcs_valid,
+    }))
+}
+
+/**
+ * Extract data from a single packet
+ *
+ * Segments of a report are collected until the report is complete.
+ * NDP Announcements and associations are tracked to link the reports of
+ * their sounding.
+ */
+fn extract_from_packet(
+    packet: &Packet,
+    reassembler: &mut SegmentReassembler,
+    ndpas: &mut NdpaTracker,
+    link_type: LinkType,
+    fcs_policy: FcsPolicy,
+) -> Result<PacketOutcome, BfaExtractionError> {
+    let frame = match extract_frame(packet, link_type, fcs_policy)? {
+        Ok(CapturedFrame::Feedback(frame)) => frame,
+        Ok(CapturedFrame::Ndpa(ndpa)) => {
+            ndpas.announce(ndpa);
+            return Ok(PacketOutcome::Announcement);
+        }
+        Ok(CapturedFrame::Association(association)) => {
+            ndpas.associate(association);
+            return Ok(PacketOutcome::Association);
+        }
+        Err(reason) => return Ok(PacketOutcome::Skipped(reason)),
+    };
+
+    match reassembler.push(frame) {
+        Some(frame) => decode_feedback(frame, ndpas),
+        None => Ok(PacketOutcome::Segment),
+    }
+}
+
+/**
+ * How to handle packets that fail to extract
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractionMode {
+    #[default]
+    Strict, // Abort on the first malformed packet
+    Lenient, // Skip malformed packets and count them in the report
+}
+
+/**
+ * Options for the extraction from a capture
+ */
+#[derive(Debug, Clone)]
+pub struct ExtractionOptions {
+    pub mode: ExtractionMode,
+    pub max_failure_indices: usize, // Packet indices to keep per error kind
+    pub fcs_policy: FcsPolicy,      // Handling of frames with a bad FCS
+    pub sounding_window: f64,       // Max. seconds between an NDPA and its reports
+}
+
+impl Default for ExtractionOptions {
+    fn default() -> Self {
+        Self {
+            mode: ExtractionMode::Strict,
+            max_failure_indices: 10,
+            fcs_policy: FcsPolicy::Drop,
+            sounding_window: DEFAULT_SOUNDING_WINDOW,
+        }
     }
 }
 
@@ -57,22 +351,92 @@ fn extract_from_packet(packet: &Packet) -> SinglePacketBfiData {
  *
  * \param capture_path Path to pcap capture file
  *
+ * Fails on the first malformed packet, reporting its index in the capture.
  */
-pub fn extract_from_capture(capture_path: PathBuf) -> ExtractedBfiData {
-    let mut capture = Capture::from_file(capture_path).expect("Couldn't open pcap file");
+pub fn extract_from_capture(capture_path: PathBuf) -> Result<ExtractedBfiData, BfaExtractionError> {
+    extract_from_capture_with_options(capture_path, &ExtractionOptions::default())
+        .map(|(extracted_data, _)| extracted_data)
+}
+
+/**
+ * Extract data from a pcap file, summarizing the run in a report
+ *
+ * \param capture_path Path to pcap capture file
+ * \param options      Extraction options
+ *
+ * Reports split into several segments are reassembled, segment sets
+ * missing segments are counted in the report. Frames with a bad FCS are
+ * dropped or kept as given by the options. Reports are linked to the
+ * latest NDP Announcement of their beamformer within the sounding window.
+ * The 802.11 frames may follow a radiotap, PPI, Prism or AVS header or
+ * none at all, other capture link types are rejected.
+ * In lenient mode, malformed packets are skipped and counted per error
+ * kind. A capture that can not be read any further ends the extraction.
+ */
+pub fn extract_from_capture_with_options(
+    capture_path: PathBuf,
+    options: &ExtractionOptions,
+) -> Result<(ExtractedBfiData, ExtractionReport), BfaExtractionError> {
+    let lenient = options.mode == ExtractionMode::Lenient;
+    let mut capture = Capture::from_file(capture_path)?;
+    let link_type = LinkType::from_linktype(capture.get_datalink())?;
     let mut extracted_data = ExtractedBfiData::new();
+    let mut report = ExtractionReport::default();
+    let mut reassembler = SegmentReassembler::default();
+    let mut ndpas = NdpaTracker::new(options.sounding_window);
 
-    while let Ok(packet) = capture.next_packet() {
-        let SinglePacketBfiData {
-            timestamp,
-            token_number,
-            bfa_angles,
-        } = extract_from_packet(&packet);
+    loop {
+        let index = report.total_packets;
+        let packet = match capture.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(e) if lenient => {
+                report.record_failure(index, &e.into(), options.max_failure_indices);
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        report.total_packets += 1;
 
-        extracted_data.timestamps.push(timestamp);
-        extracted_data.token_nums.push(token_number);
-        extracted_data.bfa_angles.push(bfa_angles);
+        match extract_from_packet(
+            &packet,
+            &mut reassembler,
+            &mut ndpas,
+            link_type,
+            options.fcs_policy,
+        ) {
+            Ok(PacketOutcome::Report(packet_data)) => {
+                report.bad_fcs_reports += (packet_data.fcs_valid == Some(false)) as usize;
+                report.mismatched_reports += packet_data
+                    .sounding
+                    .is_some_and(|sounding| sounding.parameter_mismatch)
+                    as usize;
+                extracted_data.push(packet_data);
+                report.extracted += 1;
+            }
+            Ok(PacketOutcome::Cqi(cqi_report)) => {
+                report.bad_fcs_reports += (cqi_report.fcs_valid == Some(false)) as usize;
+                extracted_data.cqi_reports.push(cqi_report);
+                report.extracted_cqi += 1;
+            }
+            Ok(PacketOutcome::Segment) | Ok(PacketOutcome::Association) => {}
+            Ok(PacketOutcome::Announcement) => report.ndp_announcements += 1,
+            Ok(PacketOutcome::Skipped(reason)) => report.record_skip(reason),
+            Err(e) if lenient => report.record_failure(index, &e, options.max_failure_indices),
+            Err(e) => {
+                return Err(BfaExtractionError::InvalidPacket {
+                    index,
+                    source: Box::new(e),
+                })
+            }
+        }
     }
 
-    extracted_data
+    reassembler.finish();
+    report.reassembled = reassembler.reassembled;
+    report.incomplete_segment_sets = reassembler.incomplete;
+    report.out_of_order_segment_sets = reassembler.out_of_order;
+    report.sounding_stats = extracted_data.sounding_sequence().stats;
+
+    Ok((extracted_data, report))
 }
@@ -0,0 +1,369 @@
+/** ------------------------------------------------------------
+ * Pseudo-headers of the supported capture link types.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::radiotap::{RadiotapFlags, RadiotapHeader};
+use crate::util::align_up;
+use pcap::Linktype;
+
+/**
+ * PPI header (version, flags, length, DLT) and field header (type, length)
+ */
+const PPI_HEADER_LENGTH: usize = 8;
+const PPI_FIELD_HEADER_LENGTH: usize = 4;
+const PPI_FLAG_ALIGNED: u8 = 0x01;
+const PPI_FIELD_80211_COMMON: u16 = 2;
+const PPI_80211_COMMON_LENGTH: usize = 20;
+const PPI_COMMON_FCS_PRESENT: u16 = 0x0001;
+const PPI_COMMON_FCS_ERROR: u16 = 0x0004;
+
+/**
+ * Prism header: message code (4), length (4) and device name (16),
+ * followed by items of did (4), status (2), length (2) and data (4)
+ */
+const PRISM_HEADER_LENGTH: usize = 144;
+const PRISM_ITEMS_START: usize = 24;
+const PRISM_ITEM_LENGTH: usize = 12;
+const PRISM_ITEM_MACTIME: usize = 1;
+const PRISM_ITEM_SIGNAL: usize = 5;
+const PRISM_ITEM_NOISE: usize = 6;
+const PRISM_ITEM_RATE: usize = 7;
+
+/**
+ * AVS header (big endian): version (4), length (4), MAC time (8),
+ * host time (8), PHY type (4), channel (4), data rate (4), antenna (4),
+ * priority (4), SSI type (4), SSI signal (4), SSI noise (4), ...
+ */
+const AVS_MIN_HEADER_LENGTH: usize = 64;
+const AVS_SSI_TYPE_DBM: u32 = 2;
+
+/**
+ * Link layer header type of a capture, all of them carrying 802.11 frames
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    Ieee80211, // Bare 802.11 frames without pseudo-header
+    Radiotap,
+    Ppi,
+    Prism,
+    Avs,
+}
+
+impl LinkType {
+    pub fn from_linktype(linktype: Linktype) -> Result<Self, BfaExtractionError> {
+        match linktype {
+            Linktype::IEEE802_11 => Ok(LinkType::Ieee80211),
+            Linktype::IEEE802_11_RADIOTAP => Ok(LinkType::Radiotap),
+            Linktype::PPI => Ok(LinkType::Ppi),
+            Linktype::IEEE802_11_PRISM => Ok(LinkType::Prism),
+            Linktype::IEEE802_11_AVS => Ok(LinkType::Avs),
+            Linktype(linktype) => Err(BfaExtractionError::UnsupportedLinkType { linktype }),
+        }
+    }
+
+    /**
+     * Parse the pseudo-header in front of the 802.11 frame
+     *
+     * The metadata of all pseudo-headers is returned as radiotap header,
+     * with its length set to the one of the pseudo-header. Fields without
+     * a counterpart are left as `None`.
+     */
+    pub fn parse_header(self, buf: &[u8]) -> Result<RadiotapHeader, BfaExtractionError> {
+        match self {
+            LinkType::Ieee80211 => Ok(RadiotapHeader::default()),
+            LinkType::Radiotap => RadiotapHeader::from_buf(buf),
+            LinkType::Ppi => parse_ppi(buf),
+            LinkType::Prism => parse_prism(buf),
+            LinkType::Avs => parse_avs(buf),
+        }
+    }
+}
+
+fn ensure_length(buf: &[u8], required: usize) -> Result<(), BfaExtractionError> {
+    if buf.len() < required {
+        return Err(BfaExtractionError::TruncatedPseudoHeader {
+            required,
+            available: buf.len(),
+        });
+    }
+    Ok(())
+}
+
+/**
+ * Ensure the length field of a pseudo-header covers at least its fixed
+ * part and at most the packet, so the 802.11 frame starts after it
+ */
+fn ensure_header_length(
+    buf: &[u8],
+    length: usize,
+    minimum: usize,
+) -> Result<(), BfaExtractionError> {
+    if length < minimum {
+        return Err(BfaExtractionError::InvalidPseudoHeaderLength { length, minimum });
+    }
+    ensure_length(buf, length)
+}
+
+/**
+ * Parse a PPI header, taking the metadata from its 802.11-Common field
+ */
+fn parse_ppi(buf: &[u8]) -> Result<RadiotapHeader, BfaExtractionError> {
+    ensure_length(buf, PPI_HEADER_LENGTH)?;
+    let u16_at = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+
+    let aligned = buf[1] & PPI_FLAG_ALIGNED != 0;
+    let length = u16_at(2) as usize;
+    ensure_header_length(buf, length, PPI_HEADER_LENGTH)?;
+
+    let dlt = i32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    if Linktype(dlt) != Linktype::IEEE802_11 {
+        return Err(BfaExtractionError::UnsupportedLinkType { linktype: dlt });
+    }
+
+    let mut header = RadiotapHeader {
+        length,
+        ..Default::default()
+    };
+
+    let mut offset = PPI_HEADER_LENGTH;
+    while offset + PPI_FIELD_HEADER_LENGTH <= length {
+        let field_type = u16_at(offset);
+        let field_length = u16_at(offset + 2) as usize;
+        let data = offset + PPI_FIELD_HEADER_LENGTH;
+        ensure_length(&buf[..length], data + field_length)?;
+
+        if field_type == PPI_FIELD_80211_COMMON && field_length >= PPI_80211_COMMON_LENGTH {
+            // TSF timer (8), flags (2), rate (2), channel frequency (2),
+            // channel flags (2), FHSS (2), signal (1), noise (1)
+            let flags = u16_at(data + 8);
+            let mut tsft = [0u8; 8];
+            tsft.copy_from_slice(&buf[data..data + 8]);
+
+            header.tsft = Some(u64::from_le_bytes(tsft));
+            header.flags = Some(RadiotapFlags::from(
+                ((flags & PPI_COMMON_FCS_PRESENT != 0) as u8) << 4
+                    | ((flags & PPI_COMMON_FCS_ERROR != 0) as u8) << 6,
+            ));
+            header.rate = Some(u16_at(data + 10) as u8);
+            header.channel_freq = Some(u16_at(data + 12));
+            header.channel_flags = Some(u16_at(data + 14));
+            header.antenna_signal = Some(buf[data + 18] as i8);
+            header.antenna_noise = Some(buf[data + 19] as i8);
+        }
+
+        offset = data + field_length;
+        if aligned {
+            offset = align_up(offset, 4);
+        }
+    }
+
+    Ok(header)
+}
+
+/**
+ * Parse a Prism header. Items with a non-zero status carry no value.
+ */
+fn parse_prism(buf: &[u8]) -> Result<RadiotapHeader, BfaExtractionError> {
+    ensure_length(buf, PRISM_HEADER_LENGTH)?;
+    let u32_at = |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+
+    let length = u32_at(4) as usize;
+    ensure_header_length(buf, length, PRISM_HEADER_LENGTH)?;
+
+    let item = |index: usize| {
+        let start = PRISM_ITEMS_START + index * PRISM_ITEM_LENGTH;
+        let status = u16::from_le_bytes([buf[start + 4], buf[start + 5]]);
+        (status == 0).then(|| u32_at(start + 8))
+    };
+
+    Ok(RadiotapHeader {
+        length,
+        tsft: item(PRISM_ITEM_MACTIME).map(u64::from),
+        rate: item(PRISM_ITEM_RATE).map(|rate| rate as u8),
+        antenna_signal: item(PRISM_ITEM_SIGNAL).map(|signal| signal as i8),
+        antenna_noise: item(PRISM_ITEM_NOISE).map(|noise| noise as i8),
+        ..Default::default()
+    })
+}
+
+/**
+ * Parse an AVS header. The data rate is given in 100 kbps units, signal
+ * and noise only count as dBm if the SSI type says so.
+ */
+fn parse_avs(buf: &[u8]) -> Result<RadiotapHeader, BfaExtractionError> {
+    ensure_length(buf, AVS_MIN_HEADER_LENGTH)?;
+    let u32_at = |i: usize| u32::from_be_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+
+    let length = u32_at(4) as usize;
+    ensure_header_length(buf, length, AVS_MIN_HEADER_LENGTH)?;
+
+    let mut mactime = [0u8; 8];
+    mactime.copy_from_slice(&buf[8..16]);
+    let dbm = u32_at(44) == AVS_SSI_TYPE_DBM;
+
+    Ok(RadiotapHeader {
+        length,
+        tsft: Some(u64::from_be_bytes(mactime)),
+        rate: Some((u32_at(32) / 5) as u8),
+        antenna_signal: dbm.then(|| u32_at(48) as i8),
+        antenna_noise: dbm.then(|| u32_at(52) as i8),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_link_type() {
+        assert_eq!(
+            LinkType::from_linktype(Linktype::IEEE802_11_RADIOTAP).unwrap(),
+            LinkType::Radiotap
+        );
+        assert!(matches!(
+            LinkType::from_linktype(Linktype::ETHERNET),
+            Err(BfaExtractionError::UnsupportedLinkType { linktype: 1 })
+        ));
+    }
+
+    #[test]
+    fn bare_80211_frames() {
+        let header = LinkType::Ieee80211.parse_header(&[0xe0, 0x00]).unwrap();
+        assert_eq!(header.length, 0);
+        assert_eq!(header.flags, None);
+    }
+
+    #[test]
+    fn ppi_80211_common() {
+        #[rustfmt::skip]
+        let byte_stream: &[u8] = &[
+            0x00, 0x00, 0x20, 0x00,     // version, flags, length 32
+            0x69, 0x00, 0x00, 0x00,     // DLT 105
+            0x02, 0x00, 0x14, 0x00,     // 802.11-Common, length 20
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // TSF timer
+            0x05, 0x00,                 // flags: FCS present, FCS error
+            0x0c, 0x00,                 // rate: 6 Mbps
+            0x9e, 0x16, 0x40, 0x01,     // channel: 5790 MHz, flags 0x0140
+            0x00, 0x00,                 // FHSS
+            0xb8, 0xa1,                 // signal -72 dBm, noise -95 dBm
+            0xe0, 0x00,                 // start of 802.11 frame
+        ];
+
+        let header = LinkType::Ppi.parse_header(byte_stream).unwrap();
+        assert_eq!(header.length, 32);
+        assert_eq!(header.tsft, Some(0x0807060504030201));
+        assert!(header.flags.unwrap().fcs_at_end());
+        assert!(header.flags.unwrap().bad_fcs());
+        assert_eq!(header.rate, Some(12));
+        assert_eq!(header.channel_freq, Some(5790));
+        assert_eq!(header.antenna_signal, Some(-72));
+        assert_eq!(header.antenna_noise, Some(-95));
+
+        // PPI encapsulating another link type
+        let mut ethernet = byte_stream.to_vec();
+        ethernet[4] = 0x01;
+        assert!(matches!(
+            LinkType::Ppi.parse_header(&ethernet),
+            Err(BfaExtractionError::UnsupportedLinkType { linktype: 1 })
+        ));
+
+        // Header length shorter than the PPI header itself
+        let mut short = byte_stream.to_vec();
+        short[2] = 0x04;
+        assert!(matches!(
+            LinkType::Ppi.parse_header(&short),
+            Err(BfaExtractionError::InvalidPseudoHeaderLength {
+                length: 4,
+                minimum: 8
+            })
+        ));
+
+        // Header length exceeding the packet
+        assert!(matches!(
+            LinkType::Ppi.parse_header(&byte_stream[..20]),
+            Err(BfaExtractionError::TruncatedPseudoHeader {
+                required: 32,
+                available: 20
+            })
+        ));
+    }
+
+    #[test]
+    fn prism_items() {
+        let mut byte_stream = vec![0u8; PRISM_HEADER_LENGTH + 2];
+        byte_stream[4] = PRISM_HEADER_LENGTH as u8;
+        for index in 0..10 {
+            let start = PRISM_ITEMS_START + index * PRISM_ITEM_LENGTH;
+            byte_stream[start + 4] = 1; // no value
+        }
+        let mut set_item = |index: usize, value: u32| {
+            let start = PRISM_ITEMS_START + index * PRISM_ITEM_LENGTH;
+            byte_stream[start + 4] = 0;
+            byte_stream[start + 8..start + 12].copy_from_slice(&value.to_le_bytes());
+        };
+        set_item(PRISM_ITEM_SIGNAL, -60i32 as u32);
+        set_item(PRISM_ITEM_RATE, 108);
+
+        let header = LinkType::Prism.parse_header(&byte_stream).unwrap();
+        assert_eq!(header.length, PRISM_HEADER_LENGTH);
+        assert_eq!(header.tsft, None);
+        assert_eq!(header.rate, Some(108));
+        assert_eq!(header.antenna_signal, Some(-60));
+        assert_eq!(header.antenna_noise, None);
+
+        // Header length within the fixed items
+        byte_stream[4] = 0;
+        assert!(matches!(
+            LinkType::Prism.parse_header(&byte_stream),
+            Err(BfaExtractionError::InvalidPseudoHeaderLength {
+                length: 0,
+                minimum: 144
+            })
+        ));
+    }
+
+    #[test]
+    fn avs_header() {
+        let mut byte_stream = vec![0u8; AVS_MIN_HEADER_LENGTH];
+        byte_stream[0..4].copy_from_slice(&0x80211001u32.to_be_bytes());
+        byte_stream[4..8].copy_from_slice(&64u32.to_be_bytes());
+        byte_stream[8..16].copy_from_slice(&1000u64.to_be_bytes());
+        byte_stream[32..36].copy_from_slice(&540u32.to_be_bytes()); // 54 Mbps
+        byte_stream[44..48].copy_from_slice(&2u32.to_be_bytes()); // SSI type dBm
+        byte_stream[48..52].copy_from_slice(&(-55i32).to_be_bytes());
+        byte_stream[52..56].copy_from_slice(&(-90i32).to_be_bytes());
+
+        let header = LinkType::Avs.parse_header(&byte_stream).unwrap();
+        assert_eq!(header.length, 64);
+        assert_eq!(header.tsft, Some(1000));
+        assert_eq!(header.rate, Some(108));
+        assert_eq!(header.antenna_signal, Some(-55));
+        assert_eq!(header.antenna_noise, Some(-90));
+
+        // Normalized RSSI is no dBm value
+        byte_stream[44..48].copy_from_slice(&1u32.to_be_bytes());
+        let header = LinkType::Avs.parse_header(&byte_stream).unwrap();
+        assert_eq!(header.antenna_signal, None);
+        assert_eq!(header.antenna_noise, None);
+
+        // Header length within the fixed fields
+        let mut short = byte_stream.clone();
+        short[4..8].copy_from_slice(&32u32.to_be_bytes());
+        assert!(matches!(
+            LinkType::Avs.parse_header(&short),
+            Err(BfaExtractionError::InvalidPseudoHeaderLength {
+                length: 32,
+                minimum: 64
+            })
+        ));
+
+        assert!(matches!(
+            LinkType::Avs.parse_header(&byte_stream[..40]),
+            Err(BfaExtractionError::TruncatedPseudoHeader {
+                required: 64,
+                available: 40
+            })
+        ));
+    }
+}
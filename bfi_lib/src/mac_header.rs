@@ -0,0 +1,232 @@
+/** ------------------------------------------------------------
+ * IEEE 802.11 MAC header extraction.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use bilge::prelude::*;
+use std::fmt;
+
+/**
+ * Length of a management frame MAC header without HT Control field
+ */
+pub const MGMT_HEADER_LENGTH: usize = 24;
+
+/**
+ * Length of the HT Control field, present if the order bit is set
+ */
+pub const HT_CONTROL_LENGTH: usize = 4;
+
+/**
+ * Frame Control field
+ */
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+pub struct FrameControl {
+    pub protocol_version: u2,
+    pub frame_type: u2,
+    pub subtype: u4,
+    pub to_ds: bool,
+    pub from_ds: bool,
+    pub more_fragments: bool,
+    pub retry: bool,
+    pub power_management: bool,
+    pub more_data: bool,
+    pub protected_frame: bool,
+    pub order: bool,
+}
+
+impl FrameControl {
+    /**
+     * Extract only the frame control from the start of a buffer. Useful to
+     * decide on the frame type before parsing the remaining header.
+     */
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < 2 {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: 2,
+                available: buf.len(),
+            });
+        }
+        Ok(FrameControl::from(u16::from_le_bytes([buf[0], buf[1]])))
+    }
+}
+
+/**
+ * Sequence Control field
+ */
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+pub struct SequenceControl {
+    pub fragment_number: u4,
+    pub sequence_number: u12,
+}
+
+/**
+ * 48 bit IEEE MAC address
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    pub fn from_buf(buf: &[u8]) -> Self {
+        let mut address = [0u8; 6];
+        address.copy_from_slice(&buf[..6]);
+        MacAddress(address)
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+/**
+ * MAC header of a management frame
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct MacHeader {
+    pub frame_control: FrameControl,
+    pub duration: u16,
+    pub receiver: MacAddress,    // Address 1 (RA)
+    pub transmitter: MacAddress, // Address 2 (TA)
+    pub bssid: MacAddress,       // Address 3 (BSSID)
+    pub sequence_control: SequenceControl,
+    pub ht_control: Option<u32>, // Only present in +HTC frames
+}
+
+impl MacHeader {
+    /**
+     * Extract the management frame MAC header from the start of a buffer
+     */
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < MGMT_HEADER_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: MGMT_HEADER_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        // In management frames, the order bit signals an HT Control field
+        // following the sequence control.
+        let frame_control = FrameControl::from_buf(buf)?;
+        let ht_control = if frame_control.order() {
+            let end = MGMT_HEADER_LENGTH + HT_CONTROL_LENGTH;
+            if buf.len() < end {
+                return Err(BfaExtractionError::TruncatedFrame {
+                    required: end,
+                    available: buf.len(),
+                });
+            }
+            Some(u32::from_le_bytes([buf[24], buf[25], buf[26], buf[27]]))
+        } else {
+            None
+        };
+
+        Ok(MacHeader {
+            frame_control,
+            duration: u16::from_le_bytes([buf[2], buf[3]]),
+            receiver: MacAddress::from_buf(&buf[4..10]),
+            transmitter: MacAddress::from_buf(&buf[10..16]),
+            bssid: MacAddress::from_buf(&buf[16..22]),
+            sequence_control: SequenceControl::from(u16::from_le_bytes([buf[22], buf[23]])),
+            ht_control,
+        })
+    }
+
+    /**
+     * Length of the MAC header in bytes, i.e. the offset to the frame body
+     */
+    pub fn length(&self) -> usize {
+        match self.ht_control {
+            Some(_) => MGMT_HEADER_LENGTH + HT_CONTROL_LENGTH,
+            None => MGMT_HEADER_LENGTH,
+        }
+    }
+
+    pub fn sequence_number(&self) -> u16 {
+        self.sequence_control.sequence_number().value()
+    }
+
+    pub fn retry(&self) -> bool {
+        self.frame_control.retry()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_header_extraction() {
+        // Action No Ack frame with retry bit set and sequence number 1234
+        #[rustfmt::skip]
+        let byte_stream: &[u8] = &[
+            0xe0, 0x08,                         // frame control
+            0x00, 0x00,                         // duration
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // receiver
+            0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // transmitter
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // bssid
+            0x23, 0x4d,                         // sequence control
+        ];
+
+        let result = MacHeader::from_buf(byte_stream).unwrap();
+        assert_eq!(result.frame_control.frame_type(), UInt::<u8, 2>::new(0));
+        assert_eq!(result.frame_control.subtype(), UInt::<u8, 4>::new(14));
+        assert!(result.retry());
+        assert!(!result.frame_control.order());
+        assert_eq!(result.receiver.to_string(), "00:11:22:33:44:55");
+        assert_eq!(result.transmitter.to_string(), "66:77:88:99:aa:bb");
+        assert_eq!(result.bssid, result.receiver);
+        assert_eq!(result.ht_control, None);
+        assert_eq!(result.length(), 24);
+        assert_eq!(result.sequence_number(), 1234);
+        assert_eq!(
+            result.sequence_control.fragment_number(),
+            UInt::<u8, 4>::new(3)
+        );
+    }
+
+    #[test]
+    fn mac_header_with_ht_control() {
+        // Order bit set: 4 byte HT Control field after sequence control
+        #[rustfmt::skip]
+        let byte_stream: &[u8] = &[
+            0xe0, 0x80,                         // frame control
+            0x00, 0x00,                         // duration
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // receiver
+            0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // transmitter
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // bssid
+            0x00, 0x00,                         // sequence control
+            0x03, 0x00, 0x00, 0x80,             // HT control
+        ];
+
+        let result = MacHeader::from_buf(byte_stream).unwrap();
+        assert!(result.frame_control.order());
+        assert_eq!(result.ht_control, Some(0x80000003));
+        assert_eq!(result.length(), 28);
+
+        // HT Control announced but missing
+        let result = MacHeader::from_buf(&byte_stream[..26]);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 28,
+                available: 26
+            })
+        ));
+    }
+
+    #[test]
+    fn mac_header_truncated() {
+        let byte_stream: &[u8] = &[0xe0, 0x00, 0x00, 0x00, 0x00, 0x11];
+        let result = MacHeader::from_buf(byte_stream);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 24,
+                available: 6
+            })
+        ));
+    }
+}
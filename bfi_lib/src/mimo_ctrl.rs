@@ -0,0 +1,172 @@
+/** ------------------------------------------------------------
+ * MIMO Control field of any supported PHY generation.
+ * ------------------------------------------------------------- */
+use crate::action_frame::PhyGeneration;
+use crate::eht_mimo_ctrl::{EhtMimoControl, EHT_MIMO_CONTROL_LENGTH};
+use crate::errors::BfaExtractionError;
+use crate::extract_bfa::ExtractionConfig;
+use crate::he_mimo_ctrl::{HeMimoControl, FEEDBACK_TYPE_SU, HE_MIMO_CONTROL_LENGTH};
+use crate::ht_mimo_ctrl::{HtMimoControl, HT_MIMO_CONTROL_LENGTH};
+use crate::vht_mimo_ctrl::{VhtMimoControl, VHT_MIMO_CONTROL_LENGTH};
+
+/**
+ * MIMO Control field preceding a compressed beamforming report
+ */
+#[derive(Debug)]
+pub enum MimoControl {
+    Ht(HtMimoControl),
+    Vht(VhtMimoControl),
+    He(HeMimoControl),
+    Eht(EhtMimoControl),
+}
+
+impl MimoControl {
+    /**
+     * Extract the MIMO Control field of the given PHY generation from the
+     * start of a buffer
+     */
+    pub fn from_buf(phy_generation: PhyGeneration, buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        match phy_generation {
+            PhyGeneration::Ht => HtMimoControl::from_buf(buf).map(MimoControl::Ht),
+            PhyGeneration::Vht => VhtMimoControl::from_buf(buf).map(MimoControl::Vht),
+            PhyGeneration::He => HeMimoControl::from_buf(buf).map(MimoControl::He),
+            PhyGeneration::Eht => EhtMimoControl::from_buf(buf).map(MimoControl::Eht),
+        }
+    }
+
+    /**
+     * Length of the MIMO Control field in bytes
+     */
+    pub fn length(&self) -> usize {
+        match self {
+            MimoControl::Ht(_) => HT_MIMO_CONTROL_LENGTH,
+            MimoControl::Vht(_) => VHT_MIMO_CONTROL_LENGTH,
+            MimoControl::He(_) => HE_MIMO_CONTROL_LENGTH,
+            MimoControl::Eht(_) => EHT_MIMO_CONTROL_LENGTH,
+        }
+    }
+
+    pub fn phy_generation(&self) -> PhyGeneration {
+        match self {
+            MimoControl::Ht(_) => PhyGeneration::Ht,
+            MimoControl::Vht(_) => PhyGeneration::Vht,
+            MimoControl::He(_) => PhyGeneration::He,
+            MimoControl::Eht(_) => PhyGeneration::Eht,
+        }
+    }
+
+    /**
+     * Feedback type, where SU (0) and MU (1) share their values across
+     * generations. HT only has SU feedback.
+     */
+    pub fn feedback_type(&self) -> u8 {
+        match self {
+            MimoControl::Ht(_) => FEEDBACK_TYPE_SU,
+            MimoControl::Vht(ctrl) => ctrl.feedback_type().value(),
+            MimoControl::He(ctrl) => ctrl.feedback_type().value(),
+            MimoControl::Eht(ctrl) => ctrl.feedback_type().value(),
+        }
+    }
+
+    pub fn remaining_feedback_segments(&self) -> u8 {
+        match self {
+            MimoControl::Ht(ctrl) => ctrl.remaining_matrix_segments().value(),
+            MimoControl::Vht(ctrl) => ctrl.remaining_feedback_segments().value(),
+            MimoControl::He(ctrl) => ctrl.remaining_feedback_segments().value(),
+            MimoControl::Eht(ctrl) => ctrl.remaining_feedback_segments().value(),
+        }
+    }
+
+    /**
+     * Whether this is the first segment of a report, None for HT which
+     * has no such flag
+     */
+    pub fn first_feedback_segment(&self) -> Option<bool> {
+        match self {
+            MimoControl::Ht(_) => None,
+            MimoControl::Vht(ctrl) => Some(ctrl.first_feedback_segments().value() == 1),
+            MimoControl::He(ctrl) => Some(ctrl.first_feedback_segments().value() == 1),
+            MimoControl::Eht(ctrl) => Some(ctrl.first_feedback_segments().value() == 1),
+        }
+    }
+
+    /**
+     * Value shared by all segments of a report and differing between
+     * soundings: the sounding dialog token, or the sounding timestamp for
+     * HT which has no token
+     */
+    pub fn segment_set_id(&self) -> u32 {
+        match self {
+            MimoControl::Ht(ctrl) => ctrl.sounding_timestamp(),
+            _ => self.dialog_token_number() as u32,
+        }
+    }
+
+    /**
+     * Sounding dialog token, HT soundings are not announced with a token
+     */
+    pub fn dialog_token_number(&self) -> u8 {
+        match self {
+            MimoControl::Ht(_) => 0,
+            MimoControl::Vht(ctrl) => ctrl.dialog_token_number().value(),
+            MimoControl::He(ctrl) => ctrl.dialog_token_number().value(),
+            MimoControl::Eht(ctrl) => ctrl.dialog_token_number().value(),
+        }
+    }
+
+    /**
+     * Get the extraction configuration of the compressed angles
+     */
+    pub fn extraction_config(&self) -> Result<ExtractionConfig, BfaExtractionError> {
+        match self {
+            MimoControl::Ht(ctrl) => ExtractionConfig::from_ht_mimo_ctrl(ctrl),
+            MimoControl::Vht(ctrl) => ExtractionConfig::from_vht_mimo_ctrl(ctrl),
+            MimoControl::He(ctrl) => ExtractionConfig::from_he_mimo_ctrl(ctrl),
+            MimoControl::Eht(ctrl) => ExtractionConfig::from_eht_mimo_ctrl(ctrl),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mimo_ctrl_dispatch() {
+        let byte_stream: &[u8] = &[0b00011001, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+
+        let he = MimoControl::from_buf(PhyGeneration::He, byte_stream).unwrap();
+        assert_eq!(he.phy_generation(), PhyGeneration::He);
+        assert_eq!(he.length(), 5);
+        assert_eq!(he.dialog_token_number(), 55);
+        assert_eq!(he.first_feedback_segment(), Some(true));
+        assert_eq!(he.extraction_config().unwrap().num_subcarrier, 64);
+
+        // Same bytes as VHT: Ng 4 at 20 MHz and dialog token 0
+        let vht = MimoControl::from_buf(PhyGeneration::Vht, byte_stream).unwrap();
+        assert_eq!(vht.phy_generation(), PhyGeneration::Vht);
+        assert_eq!(vht.length(), 3);
+        assert_eq!(vht.dialog_token_number(), 0);
+        assert_eq!(vht.extraction_config().unwrap().num_subcarrier, 16);
+
+        // Same bytes as HT: 40 MHz without grouping, with a sounding timestamp
+        let ht = MimoControl::from_buf(PhyGeneration::Ht, &[byte_stream, &[0]].concat()).unwrap();
+        assert_eq!(ht.phy_generation(), PhyGeneration::Ht);
+        assert_eq!(ht.length(), 6);
+        assert_eq!(ht.dialog_token_number(), 0);
+        assert_eq!(ht.first_feedback_segment(), None);
+        assert_eq!(ht.segment_set_id(), 0x0d_c4_00);
+        assert_eq!(ht.extraction_config().unwrap().num_subcarrier, 114);
+
+        let eht_bytes: &[u8] = &[0x31, 0x1c, 0xe8, 0xff, 0x3b];
+        let eht = MimoControl::from_buf(PhyGeneration::Eht, eht_bytes).unwrap();
+        assert_eq!(eht.phy_generation(), PhyGeneration::Eht);
+        assert_eq!(eht.length(), 5);
+        assert_eq!(eht.dialog_token_number(), 55);
+        assert_eq!(eht.remaining_feedback_segments(), 2);
+        assert!(matches!(
+            MimoControl::from_buf(PhyGeneration::Eht, &byte_stream[..4]),
+            Err(BfaExtractionError::TruncatedFrame { .. })
+        ));
+    }
+}
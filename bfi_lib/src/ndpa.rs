@@ -0,0 +1,731 @@
+/** ------------------------------------------------------------
+ * NDP Announcement extraction and linking to feedback reports.
+ * ------------------------------------------------------------- */
+use crate::action_frame::{PhyGeneration, TYPE_MANAGEMENT};
+use crate::errors::BfaExtractionError;
+use crate::he_mimo_ctrl::{FEEDBACK_TYPE_CQI, FEEDBACK_TYPE_MU, FEEDBACK_TYPE_SU};
+use crate::mac_header::{FrameControl, MacAddress, MacHeader};
+use crate::mimo_ctrl::MimoControl;
+use bilge::prelude::*;
+use std::collections::HashMap;
+
+/**
+ * Frame type and subtype of NDP Announcement frames
+ */
+pub const TYPE_CONTROL: u8 = 1;
+pub const SUBTYPE_NDPA: u8 = 5;
+
+/**
+ * Length of frame control, duration, RA, TA and sounding dialog token
+ */
+pub const NDPA_HEADER_LENGTH: usize = 17;
+
+/**
+ * Management frame subtypes of the (re)association responses assigning
+ * the AID of a station
+ */
+pub const SUBTYPE_ASSOCIATION_RESPONSE: u8 = 1;
+pub const SUBTYPE_REASSOCIATION_RESPONSE: u8 = 3;
+
+/**
+ * Length of capability information, status code and AID at the start of
+ * a (re)association response body
+ */
+const ASSOCIATION_RESPONSE_LENGTH: usize = 6;
+
+/**
+ * Default time in seconds after an NDPA within which the reports of its
+ * sounding are expected
+ */
+pub const DEFAULT_SOUNDING_WINDOW: f64 = 0.1;
+
+/**
+ * AID11 of the HE and EHT STA Info carrying a disallowed subchannel
+ * bitmap instead of a feedback request
+ */
+pub const AID_DISALLOWED_SUBCHANNELS: u16 = 2047;
+
+/**
+ * Sounding Dialog Token field, whose first two bits select the NDPA variant
+ */
+#[bitsize(8)]
+#[derive(FromBits, DebugBits)]
+struct SoundingDialogToken {
+    ranging: bool,
+    he: bool,
+    dialog_token_number: u6,
+}
+
+/**
+ * STA Info field of a VHT NDPA
+ */
+#[bitsize(16)]
+#[derive(FromBits, DebugBits)]
+struct VhtStaInfo {
+    aid: u12,
+    feedback_type: u1,
+    nc_index: u3,
+}
+
+/**
+ * STA Info field of an HE NDPA
+ */
+#[bitsize(32)]
+#[derive(FromBits, DebugBits)]
+struct HeStaInfo {
+    aid: u11,
+    ru_start_index: u7,
+    ru_end_index: u7,
+    feedback_type_ng: u2,
+    disambiguation: u1,
+    codebook_size: u1,
+    nc_index: u3,
+}
+
+/**
+ * STA Info field of an EHT NDPA
+ */
+#[bitsize(32)]
+#[derive(FromBits, DebugBits)]
+struct EhtStaInfo {
+    aid: u11,
+    partial_bw_info: u9,
+    reserved: u1,
+    nc_index: u4,
+    feedback_type_ng: u2,
+    disambiguation: u1,
+    codebook_size: u1,
+    reserved_padding: u3,
+}
+
+/**
+ * Feedback requested from one STA. Fields a VHT NDPA can not request
+ * are None.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeedbackRequest {
+    pub aid: u16,
+    pub feedback_type: u8, // Same values as in the HE MIMO Control
+    pub nc_index: u8,
+    pub grouping: Option<u8>,         // 0 for Ng 4, 1 for Ng 16
+    pub codebook_info: Option<u8>,    // As in the MIMO Control
+    pub ru_span: Option<(u8, u8)>,    // HE RU start and end index
+    pub partial_bw_info: Option<u16>, // EHT partial bandwidth info
+}
+
+impl FeedbackRequest {
+    /**
+     * Decode the combined Feedback Type And Ng and Codebook Size
+     * subfields of HE and EHT STA Infos into feedback type, grouping
+     * and codebook information. See IEEE 802.11ax Table 9-31f.
+     */
+    fn decode_feedback_type_ng(
+        feedback_type_ng: u8,
+        codebook_size: u8,
+    ) -> (u8, Option<u8>, Option<u8>) {
+        match (feedback_type_ng, codebook_size) {
+            (0, _) => (FEEDBACK_TYPE_SU, Some(0), Some(codebook_size)),
+            (1, _) => (FEEDBACK_TYPE_SU, Some(1), Some(codebook_size)),
+            (2, _) => (FEEDBACK_TYPE_MU, Some(0), Some(codebook_size)),
+            (_, 0) => (FEEDBACK_TYPE_MU, Some(1), Some(1)),
+            (_, _) => (FEEDBACK_TYPE_CQI, None, None),
+        }
+    }
+
+    fn from_vht(sta_info: VhtStaInfo) -> Self {
+        FeedbackRequest {
+            aid: sta_info.aid().value(),
+            feedback_type: sta_info.feedback_type().value(),
+            nc_index: sta_info.nc_index().value(),
+            grouping: None,
+            codebook_info: None,
+            ru_span: None,
+            partial_bw_info: None,
+        }
+    }
+
+    fn from_he(sta_info: HeStaInfo) -> Self {
+        let (feedback_type, grouping, codebook_info) = Self::decode_feedback_type_ng(
+            sta_info.feedback_type_ng().value(),
+            sta_info.codebook_size().value(),
+        );
+        FeedbackRequest {
+            aid: sta_info.aid().value(),
+            feedback_type,
+            nc_index: sta_info.nc_index().value(),
+            grouping,
+            codebook_info,
+            ru_span: Some((
+                sta_info.ru_start_index().value(),
+                sta_info.ru_end_index().value(),
+            )),
+            partial_bw_info: None,
+        }
+    }
+
+    fn from_eht(sta_info: EhtStaInfo) -> Self {
+        let (feedback_type, grouping, codebook_info) = Self::decode_feedback_type_ng(
+            sta_info.feedback_type_ng().value(),
+            sta_info.codebook_size().value(),
+        );
+        FeedbackRequest {
+            aid: sta_info.aid().value(),
+            feedback_type,
+            nc_index: sta_info.nc_index().value(),
+            grouping,
+            codebook_info,
+            ru_span: None,
+            partial_bw_info: Some(sta_info.partial_bw_info().value()),
+        }
+    }
+
+    /**
+     * Whether a report was sent with the requested parameters. For CQI
+     * feedback, only the feedback type and the RU span are compared.
+     */
+    pub fn is_met_by(&self, mimo_control: &MimoControl) -> bool {
+        let (nc_index, grouping, codebook_info, ru_span, partial_bw_info) = match mimo_control {
+            MimoControl::Ht(_) => return false,
+            MimoControl::Vht(ctrl) => (ctrl.nc_index().value(), None, None, None, None),
+            MimoControl::He(ctrl) => (
+                ctrl.nc_index().value(),
+                Some(ctrl.grouping().value()),
+                Some(ctrl.codebook_info().value()),
+                Some((ctrl.ru_start_index().value(), ctrl.ru_end_index().value())),
+                None,
+            ),
+            MimoControl::Eht(ctrl) => (
+                ctrl.nc_index().value(),
+                Some(ctrl.grouping().value()),
+                Some(ctrl.codebook_info().value()),
+                None,
+                Some(ctrl.partial_bw_info().value()),
+            ),
+        };
+
+        // Parameters not requested by the NDPA always match
+        fn matches<T: PartialEq>(requested: Option<T>, reported: Option<T>) -> bool {
+            requested.is_none() || requested == reported
+        }
+
+        let feedback_type = mimo_control.feedback_type();
+        if feedback_type == FEEDBACK_TYPE_CQI && self.feedback_type == FEEDBACK_TYPE_CQI {
+            return matches(self.ru_span, ru_span);
+        }
+
+        self.feedback_type == feedback_type
+            && self.nc_index == nc_index
+            && matches(self.grouping, grouping)
+            && matches(self.codebook_info, codebook_info)
+            && matches(self.ru_span, ru_span)
+            && matches(self.partial_bw_info, partial_bw_info)
+    }
+}
+
+/**
+ * NDP Announcement frame starting a sounding sequence
+ */
+#[derive(Debug, Clone)]
+pub struct Ndpa {
+    pub timestamp: f64,
+    pub phy_generation: PhyGeneration,
+    pub receiver: MacAddress,
+    pub beamformer: MacAddress,
+    pub dialog_token_number: u8,
+    pub sta_infos: Vec<FeedbackRequest>,
+}
+
+/**
+ * Check whether a frame is an NDP Announcement
+ */
+pub fn is_ndpa(frame_control: &FrameControl) -> bool {
+    frame_control.frame_type().value() == TYPE_CONTROL
+        && frame_control.subtype().value() == SUBTYPE_NDPA
+}
+
+impl Ndpa {
+    /**
+     * Extract an NDP Announcement from a frame without FCS
+     *
+     * \returns None for ranging NDPAs, which do not request feedback
+     */
+    pub fn from_buf(buf: &[u8], timestamp: f64) -> Result<Option<Self>, BfaExtractionError> {
+        if buf.len() < NDPA_HEADER_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: NDPA_HEADER_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        let token = SoundingDialogToken::from(buf[16]);
+        let phy_generation = match (token.ranging(), token.he()) {
+            (false, false) => PhyGeneration::Vht,
+            (false, true) => PhyGeneration::He,
+            (true, true) => PhyGeneration::Eht,
+            (true, false) => return Ok(None),
+        };
+
+        let sta_infos = &buf[NDPA_HEADER_LENGTH..];
+        let sta_infos = match phy_generation {
+            PhyGeneration::Vht => sta_infos
+                .chunks_exact(2)
+                .map(|info| VhtStaInfo::from(u16::from_le_bytes([info[0], info[1]])))
+                .map(FeedbackRequest::from_vht)
+                .collect(),
+            _ => sta_infos
+                .chunks_exact(4)
+                .map(|info| u32::from_le_bytes([info[0], info[1], info[2], info[3]]))
+                .map(|info| match phy_generation {
+                    PhyGeneration::Eht => FeedbackRequest::from_eht(EhtStaInfo::from(info)),
+                    _ => FeedbackRequest::from_he(HeStaInfo::from(info)),
+                })
+                .filter(|info| info.aid != AID_DISALLOWED_SUBCHANNELS)
+                .collect(),
+        };
+
+        // With bandwidth signaling, the individual/group bit of the TA is set
+        let mut beamformer = MacAddress::from_buf(&buf[10..16]);
+        beamformer.0[0] &= !1;
+
+        Ok(Some(Ndpa {
+            timestamp,
+            phy_generation,
+            receiver: MacAddress::from_buf(&buf[4..10]),
+            beamformer,
+            dialog_token_number: token.dialog_token_number().value(),
+            sta_infos,
+        }))
+    }
+
+    /**
+     * Find the feedback requested from a beamformee. A STA is only
+     * identified by its AID in the STA Info, so the request is only
+     * known if the NDPA polls a single STA or the AID of the beamformee
+     * is known.
+     */
+    fn request_for(&self, aid: Option<u16>) -> Option<FeedbackRequest> {
+        match self.sta_infos.as_slice() {
+            [sta_info] => Some(*sta_info),
+            sta_infos => aid.and_then(|aid| sta_infos.iter().find(|info| info.aid == aid).copied()),
+        }
+    }
+}
+
+/**
+ * Successful (re)association of a station, assigning its AID
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Association {
+    pub access_point: MacAddress,
+    pub station: MacAddress,
+    pub aid: u16,
+}
+
+/**
+ * Check whether a frame is a (re)association response
+ */
+pub fn is_association_response(frame_control: &FrameControl) -> bool {
+    frame_control.frame_type().value() == TYPE_MANAGEMENT
+        && matches!(
+            frame_control.subtype().value(),
+            SUBTYPE_ASSOCIATION_RESPONSE | SUBTYPE_REASSOCIATION_RESPONSE
+        )
+}
+
+impl Association {
+    /**
+     * Extract the AID assigned by a (re)association response without FCS
+     *
+     * \returns None if the association failed
+     */
+    pub fn from_buf(buf: &[u8]) -> Result<Option<Self>, BfaExtractionError> {
+        let mac_header = MacHeader::from_buf(buf)?;
+        let body_start = mac_header.length();
+        let required = body_start + ASSOCIATION_RESPONSE_LENGTH;
+        if buf.len() < required {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required,
+                available: buf.len(),
+            });
+        }
+
+        let u16_at = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+        if u16_at(body_start + 2) != 0 {
+            return Ok(None);
+        }
+
+        // The two most significant bits of the AID field are set
+        Ok(Some(Association {
+            access_point: mac_header.transmitter,
+            station: mac_header.receiver,
+            aid: u16_at(body_start + 4) & 0x3fff,
+        }))
+    }
+}
+
+/**
+ * Compose an HE NDP Announcement frame without FCS, requesting the given
+ * feedback from every STA. The feedback requests must carry the HE
+ * grouping, codebook and RU span.
+ */
+pub fn compose_he_ndpa(
+    receiver: MacAddress,
+    beamformer: MacAddress,
+    dialog_token_number: u8,
+    sta_infos: &[FeedbackRequest],
+) -> Vec<u8> {
+    let mut frame = vec![SUBTYPE_NDPA << 4 | TYPE_CONTROL << 2, 0, 0, 0];
+    frame.extend(receiver.0);
+    frame.extend(beamformer.0);
+    frame.push(SoundingDialogToken::new(false, true, u6::new(dialog_token_number & 0x3f)).into());
+
+    for request in sta_infos {
+        let grouping = request.grouping.unwrap_or(0);
+        let codebook_info = request.codebook_info.unwrap_or(0);
+        let (feedback_type_ng, codebook_size) = match request.feedback_type {
+            FEEDBACK_TYPE_SU => (grouping, codebook_info),
+            FEEDBACK_TYPE_MU if grouping == 0 => (2, codebook_info),
+            FEEDBACK_TYPE_MU => (3, 0),
+            _ => (3, 1),
+        };
+        let (ru_start_index, ru_end_index) = request.ru_span.unwrap_or((0, 0));
+
+        let sta_info = HeStaInfo::new(
+            u11::new(request.aid),
+            u7::new(ru_start_index),
+            u7::new(ru_end_index),
+            u2::new(feedback_type_ng),
+            u1::new(1),
+            u1::new(codebook_size),
+            u3::new(request.nc_index),
+        );
+        frame.extend(u32::from(sta_info).to_le_bytes());
+    }
+
+    frame
+}
+
+/**
+ * Sounding context of a report, taken from the NDPA announcing it
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoundingInfo {
+    pub ndpa_timestamp: f64,
+    pub beamformer: MacAddress,
+    pub requested: Option<FeedbackRequest>, // None if the STA Info is ambiguous
+    pub parameter_mismatch: bool,           // Report differs from the request
+}
+
+/**
+ * Most recent NDP Announcement per beamformer, along with the AIDs of
+ * the beamformees
+ *
+ * A new announcement supersedes the previous one of the beamformer, and
+ * reports are only linked within the sounding window after it. This keeps
+ * a report from being linked to a stale NDPA once the dialog token wraps.
+ */
+pub struct NdpaTracker {
+    latest: HashMap<MacAddress, Ndpa>,
+    aids: HashMap<(MacAddress, MacAddress), u16>, // (beamformer, beamformee)
+    window: f64,
+}
+
+impl Default for NdpaTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_SOUNDING_WINDOW)
+    }
+}
+
+impl NdpaTracker {
+    /**
+     * Create a tracker linking reports up to window seconds after their
+     * NDPA
+     */
+    pub fn new(window: f64) -> Self {
+        Self {
+            latest: HashMap::new(),
+            aids: HashMap::new(),
+            window,
+        }
+    }
+
+    pub fn announce(&mut self, ndpa: Ndpa) {
+        // An NDPA addressed to a single STA reveals its AID as well
+        if let [sta_info] = ndpa.sta_infos.as_slice() {
+            if ndpa.receiver.0[0] & 1 == 0 && sta_info.aid != 0 {
+                self.aids
+                    .insert((ndpa.beamformer, ndpa.receiver), sta_info.aid);
+            }
+        }
+        self.latest.insert(ndpa.beamformer, ndpa);
+    }
+
+    pub fn associate(&mut self, association: Association) {
+        self.aids.insert(
+            (association.access_point, association.station),
+            association.aid,
+        );
+    }
+
+    /**
+     * Link a report sent by the beamformee to the beamformer at the given
+     * time to the NDPA announcing its sounding, matching the dialog token.
+     * The STA Info of the beamformee is found by its AID.
+     */
+    pub fn link(
+        &self,
+        timestamp: f64,
+        beamformee: MacAddress,
+        beamformer: MacAddress,
+        mimo_control: &MimoControl,
+    ) -> Option<SoundingInfo> {
+        let ndpa = self.latest.get(&beamformer).filter(|ndpa| {
+            ndpa.dialog_token_number == mimo_control.dialog_token_number()
+                && ndpa.phy_generation == mimo_control.phy_generation()
+                && (0.0..=self.window).contains(&(timestamp - ndpa.timestamp))
+        })?;
+
+        let aid = self.aids.get(&(beamformer, beamformee)).copied();
+        let requested = ndpa.request_for(aid);
+        Some(SoundingInfo {
+            ndpa_timestamp: ndpa.timestamp,
+            beamformer: ndpa.beamformer,
+            requested,
+            parameter_mismatch: requested.is_some_and(|request| !request.is_met_by(mimo_control)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::he_mimo_ctrl::HeMimoControl;
+
+    #[rustfmt::skip]
+    const HE_NDPA: &[u8] = &[
+        0x54, 0x00,                         // frame control
+        0x00, 0x00,                         // duration
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // receiver
+        0x67, 0x77, 0x88, 0x99, 0xaa, 0xbb, // transmitter (bandwidth signaling)
+        0xde,                               // HE, dialog token 55
+        0x01, 0x00, 0x20, 0x08,             // AID 1, RU 0 to 8, SU Ng 4, Nc index 0
+        0xff, 0xff, 0xff, 0xff,             // disallowed subchannels
+    ];
+
+    #[test]
+    fn he_ndpa_extraction() {
+        let ndpa = Ndpa::from_buf(HE_NDPA, 1.0).unwrap().unwrap();
+        assert_eq!(ndpa.phy_generation, PhyGeneration::He);
+        assert_eq!(ndpa.dialog_token_number, 55);
+        assert_eq!(ndpa.beamformer.to_string(), "66:77:88:99:aa:bb");
+        assert_eq!(
+            ndpa.sta_infos,
+            vec![FeedbackRequest {
+                aid: 1,
+                feedback_type: FEEDBACK_TYPE_SU,
+                nc_index: 0,
+                grouping: Some(0),
+                codebook_info: Some(0),
+                ru_span: Some((0, 8)),
+                partial_bw_info: None,
+            }]
+        );
+
+        // Ranging NDPA
+        let mut ranging = HE_NDPA.to_vec();
+        ranging[16] = 0xdd;
+        assert!(Ndpa::from_buf(&ranging, 1.0).unwrap().is_none());
+
+        assert!(matches!(
+            Ndpa::from_buf(&HE_NDPA[..16], 1.0),
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 17,
+                available: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn compose_he_ndpa_round_trip() {
+        let receiver = MacAddress::from_buf(&HE_NDPA[4..10]);
+        let beamformer = MacAddress::from_buf(&HE_NDPA[10..16]);
+        let request = FeedbackRequest {
+            aid: 1,
+            feedback_type: FEEDBACK_TYPE_SU,
+            nc_index: 0,
+            grouping: Some(0),
+            codebook_info: Some(0),
+            ru_span: Some((0, 8)),
+            partial_bw_info: None,
+        };
+        let frame = compose_he_ndpa(receiver, beamformer, 55, &[request]);
+        assert_eq!(frame, HE_NDPA[..21]);
+
+        // MU with Ng 16 implies codebook 1, CQI carries neither
+        let requests = [
+            FeedbackRequest {
+                aid: 2,
+                feedback_type: FEEDBACK_TYPE_MU,
+                nc_index: 3,
+                grouping: Some(1),
+                codebook_info: Some(1),
+                ru_span: Some((4, 17)),
+                partial_bw_info: None,
+            },
+            FeedbackRequest {
+                aid: 3,
+                feedback_type: FEEDBACK_TYPE_CQI,
+                nc_index: 1,
+                grouping: None,
+                codebook_info: None,
+                ru_span: Some((0, 36)),
+                partial_bw_info: None,
+            },
+        ];
+        let frame = compose_he_ndpa(receiver, beamformer, 9, &requests);
+        let ndpa = Ndpa::from_buf(&frame, 0.0).unwrap().unwrap();
+        assert_eq!(ndpa.dialog_token_number, 9);
+        assert_eq!(ndpa.sta_infos, requests);
+    }
+
+    #[test]
+    fn vht_ndpa_extraction() {
+        let mut vht_ndpa = HE_NDPA[..17].to_vec();
+        vht_ndpa[16] = 0xdc;
+        vht_ndpa.extend([0x05, 0x30, 0x06, 0x10]);
+
+        let ndpa = Ndpa::from_buf(&vht_ndpa, 1.0).unwrap().unwrap();
+        assert_eq!(ndpa.phy_generation, PhyGeneration::Vht);
+        assert_eq!(ndpa.sta_infos.len(), 2);
+        assert_eq!(ndpa.sta_infos[0].aid, 5);
+        assert_eq!(ndpa.sta_infos[0].feedback_type, FEEDBACK_TYPE_MU);
+        assert_eq!(ndpa.sta_infos[0].nc_index, 1);
+        assert_eq!(ndpa.sta_infos[1].grouping, None);
+    }
+
+    #[test]
+    fn link_report_to_ndpa() {
+        let mut tracker = NdpaTracker::default();
+        tracker.announce(Ndpa::from_buf(HE_NDPA, 1.0).unwrap().unwrap());
+
+        let beamformee = MacAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        let beamformer = MacAddress([0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]);
+
+        // Nc index 0, Nr index 3, BW 20, Ng 4, SU, RU 0 to 8, token 55
+        let requested = [0b00011000, 0b10000000, 0b00000000, 0b11000100, 0b00001101];
+        let mimo_control = MimoControl::He(HeMimoControl::from_buf(&requested).unwrap());
+        let sounding = tracker
+            .link(1.01, beamformee, beamformer, &mimo_control)
+            .unwrap();
+        assert_eq!(sounding.ndpa_timestamp, 1.0);
+        assert_eq!(sounding.requested.unwrap().aid, 1);
+        assert!(!sounding.parameter_mismatch);
+
+        // Nc index 1 instead of 0
+        let two_columns = [0b00011001, 0b10000000, 0b00000000, 0b11000100, 0b00001101];
+        let mimo_control = MimoControl::He(HeMimoControl::from_buf(&two_columns).unwrap());
+        let sounding = tracker
+            .link(1.01, beamformee, beamformer, &mimo_control)
+            .unwrap();
+        assert!(sounding.parameter_mismatch);
+
+        // Another dialog token
+        let other_token = [0b00011000, 0b10000000, 0b00000000, 0b11000100, 0b00001110];
+        let mimo_control = MimoControl::He(HeMimoControl::from_buf(&other_token).unwrap());
+        assert!(tracker
+            .link(1.01, beamformee, beamformer, &mimo_control)
+            .is_none());
+
+        // Report outside of the sounding window, e.g. after the token wrapped
+        let mimo_control = MimoControl::He(HeMimoControl::from_buf(&requested).unwrap());
+        assert!(tracker
+            .link(1.2, beamformee, beamformer, &mimo_control)
+            .is_none());
+        assert!(tracker
+            .link(0.9, beamformee, beamformer, &mimo_control)
+            .is_none());
+
+        // Superseded by the next sounding of the beamformer
+        let mut next = HE_NDPA.to_vec();
+        next[16] = 0xe2;
+        tracker.announce(Ndpa::from_buf(&next, 1.02).unwrap().unwrap());
+        assert!(tracker
+            .link(1.03, beamformee, beamformer, &mimo_control)
+            .is_none());
+    }
+
+    #[test]
+    fn link_multi_sta_report() {
+        let beamformer = MacAddress([0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]);
+        let beamformee = MacAddress([0x02, 0, 0, 0, 0, 0x02]);
+        let request = |aid, nc_index| FeedbackRequest {
+            aid,
+            feedback_type: FEEDBACK_TYPE_SU,
+            nc_index,
+            grouping: Some(0),
+            codebook_info: Some(0),
+            ru_span: Some((0, 8)),
+            partial_bw_info: None,
+        };
+        let frame = compose_he_ndpa(
+            MacAddress([0xff; 6]),
+            beamformer,
+            55,
+            &[request(1, 1), request(2, 0)],
+        );
+
+        let mut tracker = NdpaTracker::default();
+        tracker.announce(Ndpa::from_buf(&frame, 1.0).unwrap().unwrap());
+
+        // Nc index 0, Nr index 3, BW 20, Ng 4, SU, RU 0 to 8, token 55
+        let report = [0b00011000, 0b10000000, 0b00000000, 0b11000100, 0b00001101];
+        let mimo_control = MimoControl::He(HeMimoControl::from_buf(&report).unwrap());
+
+        // The STA Info is unknown without the AID of the beamformee
+        let sounding = tracker
+            .link(1.01, beamformee, beamformer, &mimo_control)
+            .unwrap();
+        assert_eq!(sounding.requested, None);
+
+        #[rustfmt::skip]
+        let association_response: &[u8] = &[
+            0x10, 0x00, 0x00, 0x00,             // frame control, duration
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x02, // receiver
+            0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // transmitter
+            0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // BSSID
+            0x00, 0x00,                         // sequence control
+            0x11, 0x04,                         // capability information
+            0x00, 0x00,                         // status: success
+            0x02, 0xc0,                         // AID 2
+        ];
+        let frame_control = FrameControl::from_buf(association_response).unwrap();
+        assert!(is_association_response(&frame_control));
+        let association = Association::from_buf(association_response)
+            .unwrap()
+            .unwrap();
+        assert_eq!(association.station, beamformee);
+        assert_eq!(association.aid, 2);
+        tracker.associate(association);
+
+        let sounding = tracker
+            .link(1.01, beamformee, beamformer, &mimo_control)
+            .unwrap();
+        assert_eq!(sounding.requested, Some(request(2, 0)));
+        assert!(!sounding.parameter_mismatch);
+
+        // The STA Info of AID 1 requests two columns
+        tracker.associate(Association {
+            aid: 1,
+            ..association
+        });
+        let sounding = tracker
+            .link(1.01, beamformee, beamformer, &mimo_control)
+            .unwrap();
+        assert!(sounding.parameter_mismatch);
+
+        // Failed association
+        let mut refused = association_response.to_vec();
+        refused[26] = 0x01;
+        assert_eq!(Association::from_buf(&refused).unwrap(), None);
+    }
+}
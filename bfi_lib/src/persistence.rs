@@ -1,18 +1,53 @@
 /** ------------------------------------------------------------
  * Persistence (saving extracted data to parquet files)
  * ------------------------------------------------------------- */
-use crate::bfi_data::ExtractedBfiData;
+use crate::angles::AngleLayout;
+use crate::bfi_data::{CqiReport, ExtractedBfiData};
+use crate::mac_header::MacAddress;
+use crate::ndpa::FeedbackRequest;
+use crate::radiotap::RadiotapHeader;
 use crate::PathBuf;
 use std::fs::File;
+use std::path::Path;
 
 use polars::prelude::*;
 use polars::{datatypes::ListChunked, error::PolarsError, frame::DataFrame, series::Series};
 
+/**
+ * Options for writing extracted data to parquet
+ */
+#[derive(Debug, Default, Clone)]
+pub struct ParquetOptions {
+    pub bfa_radians: bool, // Add the angles in radians as bfa_radians column
+    pub v_matrix: bool,    // Add V per subcarrier as v_real and v_imag columns
+}
+
+/**
+ * Path of the sibling file holding the CQI-only reports, e.g.
+ * capture.cqi.parquet next to capture.parquet
+ */
+pub fn cqi_parquet_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension("cqi.parquet")
+}
+
 /**
  * Parquet conversion of extracted BFI data
  */
 impl ExtractedBfiData {
     pub fn to_parquet(&self, file_path: PathBuf) -> Result<(), PolarsError> {
+        self.to_parquet_with_options(file_path, &ParquetOptions::default())
+    }
+
+    /**
+     * Write the beamforming reports to a parquet file. CQI-only reports
+     * carry no angles and are written to a sibling file, see
+     * cqi_parquet_path.
+     */
+    pub fn to_parquet_with_options(
+        &self,
+        file_path: PathBuf,
+        options: &ParquetOptions,
+    ) -> Result<(), PolarsError> {
         // Convert timestamps and token_nums to Polars Series
         let timestamps_series = Series::new("timestamps", &self.timestamps);
 
@@ -27,6 +62,18 @@ impl ExtractedBfiData {
                 .collect::<Vec<u32>>(),
         );
 
+        // Unwrapped dialog tokens, monotonic per beamformer, null for HT
+        let sounding_seqs_series =
+            Series::new("sounding_seqs", &self.sounding_sequence().unwrapped_tokens);
+
+        let phy_generations_series = Series::new(
+            "phy_generations",
+            self.phy_generations
+                .iter()
+                .map(|generation| generation.to_string())
+                .collect::<Vec<String>>(),
+        );
+
         // Convert Vec<Vec<u8>> to a List Series of Vec<i32>
         let bfa_angles_series = ListChunked::from_iter(self.bfa_angles.iter().map(|outer| {
             ListChunked::from_iter(outer.iter().map(|inner| {
@@ -40,17 +87,332 @@ impl ExtractedBfiData {
         }))
         .into_series();
 
+        // Dimensions and bit sizes needed to interpret the angles
+        let layout_series = |name: &str, field: fn(&AngleLayout) -> u32| {
+            Series::new(
+                name,
+                &self.angle_layouts.iter().map(field).collect::<Vec<u32>>(),
+            )
+        };
+
+        // Delta SNR is empty for SU feedback
+        let delta_snr_series = ListChunked::from_iter(self.delta_snr.iter().map(|outer| {
+            ListChunked::from_iter(outer.iter().map(|inner| {
+                Int32Chunked::from_vec(
+                    "delta_snr_inner",
+                    inner.iter().map(|&e| e as i32).collect::<Vec<i32>>(),
+                )
+                .into_series()
+            }))
+            .into_series()
+        }))
+        .into_series()
+        .with_name("delta_snr");
+
+        // Tone indices are widened to 32 bit, like all small integer types
+        let tone_indices_series = ListChunked::from_iter(self.tone_indices.iter().map(|tones| {
+            Int32Chunked::from_vec(
+                "tone_indices_inner",
+                tones.iter().map(|&tone| tone as i32).collect::<Vec<i32>>(),
+            )
+            .into_series()
+        }))
+        .into_series()
+        .with_name("tone_indices");
+
+        let stream_snr_series =
+            ListChunked::from_iter(self.stream_snr.iter().map(|snr| {
+                Float32Chunked::from_vec("stream_snr_inner", snr.clone()).into_series()
+            }))
+            .into_series()
+            .with_name("stream_snr");
+
+        // MAC addresses are stored in their usual string representation
+        let address_series = |name: &str, addresses: &[MacAddress]| {
+            Series::new(
+                name,
+                addresses
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        let sequence_nums_series = Series::new(
+            "sequence_nums",
+            &self
+                .sequence_nums
+                .iter()
+                .map(|&num| num as u32)
+                .collect::<Vec<u32>>(),
+        );
+
+        // Radiotap fields are optional, missing ones are stored as null.
+        // Widen to 64 bit, which holds the TSF timer as well as the 8 and
+        // 16 bit fields polars doesnt support.
+        let radiotap_series = |name: &str, field: fn(&RadiotapHeader) -> Option<i64>| {
+            Series::new(
+                name,
+                &self
+                    .radiotap
+                    .iter()
+                    .map(field)
+                    .collect::<Vec<Option<i64>>>(),
+            )
+        };
+
+        // The sounding context is null for reports without a captured NDPA,
+        // the requested parameters also if the NDPA polls several STAs and
+        // the AID of the beamformee is unknown.
+        let ndpa_timestamps_series = Series::new(
+            "ndpa_timestamps",
+            &self
+                .soundings
+                .iter()
+                .map(|sounding| sounding.map(|s| s.ndpa_timestamp))
+                .collect::<Vec<Option<f64>>>(),
+        );
+
+        let beamformers_series = Series::new(
+            "beamformers",
+            self.soundings
+                .iter()
+                .map(|sounding| sounding.map(|s| s.beamformer.to_string()))
+                .collect::<Vec<Option<String>>>(),
+        );
+
+        let parameter_mismatch_series = Series::new(
+            "parameter_mismatch",
+            &self
+                .soundings
+                .iter()
+                .map(|sounding| sounding.map(|s| s.parameter_mismatch))
+                .collect::<Vec<Option<bool>>>(),
+        );
+
+        let requested_series = |name: &str, field: fn(&FeedbackRequest) -> Option<u32>| {
+            Series::new(
+                name,
+                &self
+                    .soundings
+                    .iter()
+                    .map(|sounding| sounding.and_then(|s| s.requested.as_ref().and_then(field)))
+                    .collect::<Vec<Option<u32>>>(),
+            )
+        };
+
+        // Null where the FCS could not be verified
+        let fcs_valid_series = Series::new("fcs_valid", &self.fcs_valid);
+
         // Construct DataFrame from the series
         let mut df = DataFrame::new(vec![
             timestamps_series,
             token_nums_series,
+            sounding_seqs_series,
+            phy_generations_series,
             bfa_angles_series,
+            layout_series("nr", |l| l.nr as u32),
+            layout_series("nc", |l| l.nc as u32),
+            layout_series("phi_bit", |l| l.phi_bit.into()),
+            layout_series("psi_bit", |l| l.psi_bit.into()),
+            delta_snr_series,
+            tone_indices_series,
+            stream_snr_series,
+            address_series("transmitters", &self.transmitters),
+            address_series("receivers", &self.receivers),
+            sequence_nums_series,
+            fcs_valid_series,
+            ndpa_timestamps_series,
+            beamformers_series,
+            requested_series("requested_feedback_type", |r| Some(r.feedback_type.into())),
+            requested_series("requested_nc_index", |r| Some(r.nc_index.into())),
+            requested_series("requested_grouping", |r| r.grouping.map(u32::from)),
+            requested_series("requested_codebook_info", |r| {
+                r.codebook_info.map(u32::from)
+            }),
+            requested_series("requested_ru_start_index", |r| {
+                r.ru_span.map(|(start, _)| start.into())
+            }),
+            requested_series("requested_ru_end_index", |r| {
+                r.ru_span.map(|(_, end)| end.into())
+            }),
+            requested_series("requested_partial_bw_info", |r| {
+                r.partial_bw_info.map(u32::from)
+            }),
+            parameter_mismatch_series,
+            radiotap_series("tsft", |r| r.tsft.map(|v| v as i64)),
+            radiotap_series("flags", |r| r.flags.map(|v| u8::from(v) as i64)),
+            radiotap_series("rate", |r| r.rate.map(i64::from)),
+            radiotap_series("channel_freq", |r| r.channel_freq.map(i64::from)),
+            radiotap_series("channel_flags", |r| r.channel_flags.map(i64::from)),
+            radiotap_series("antenna_signal", |r| r.antenna_signal.map(i64::from)),
+            radiotap_series("antenna_noise", |r| r.antenna_noise.map(i64::from)),
+            radiotap_series("mcs", |r| r.mcs.map(i64::from)),
         ])?;
 
+        if options.bfa_radians {
+            let bfa_radians_series =
+                ListChunked::from_iter(self.bfa_radians().into_iter().map(|outer| {
+                    ListChunked::from_iter(outer.into_iter().map(|inner| {
+                        Float64Chunked::from_vec("bfa_radians_inner", inner).into_series()
+                    }))
+                    .into_series()
+                }))
+                .into_series()
+                .with_name("bfa_radians");
+            df.with_column(bfa_radians_series)?;
+        }
+
+        // V is stored in row-major order, split into real and imaginary part
+        if options.v_matrix {
+            let v_matrices = self.v_matrices();
+            let v_series = |name: &str, part: usize| {
+                ListChunked::from_iter(v_matrices.iter().map(|report| {
+                    ListChunked::from_iter(report.iter().map(|v| {
+                        Float64Chunked::from_vec(
+                            "v_inner",
+                            v.iter().skip(part).step_by(2).copied().collect(),
+                        )
+                        .into_series()
+                    }))
+                    .into_series()
+                }))
+                .into_series()
+                .with_name(name)
+            };
+            df.with_column(v_series("v_real", 0))?;
+            df.with_column(v_series("v_imag", 1))?;
+        }
+
+        if !self.cqi_reports.is_empty() {
+            self.cqi_to_parquet(cqi_parquet_path(&file_path))?;
+        }
+
         // Write DataFrame to a Parquet file
         let file = File::create(file_path)?;
         ParquetWriter::new(file).finish(&mut df)?;
 
         Ok(())
     }
+
+    /**
+     * Write the CQI-only reports to a parquet file, one row per report
+     * with the CQI index per 26-tone RU and stream
+     */
+    pub fn cqi_to_parquet(&self, file_path: PathBuf) -> Result<(), PolarsError> {
+        let reports = &self.cqi_reports;
+
+        // Widen to 32 bit, since polars doesnt support 8 and 16 bit types
+        let field_series = |name: &str, field: fn(&CqiReport) -> u32| {
+            Series::new(name, &reports.iter().map(field).collect::<Vec<u32>>())
+        };
+
+        let address_series = |name: &str, address: fn(&CqiReport) -> MacAddress| {
+            Series::new(
+                name,
+                reports
+                    .iter()
+                    .map(|report| address(report).to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        let cqi_series = ListChunked::from_iter(reports.iter().map(|report| {
+            ListChunked::from_iter(report.cqi.iter().map(|ru| {
+                UInt32Chunked::from_vec("cqi_inner", ru.iter().map(|&cqi| cqi as u32).collect())
+                    .into_series()
+            }))
+            .into_series()
+        }))
+        .into_series()
+        .with_name("cqi");
+
+        let mut df = DataFrame::new(vec![
+            Series::new(
+                "timestamps",
+                &reports.iter().map(|r| r.timestamp).collect::<Vec<f64>>(),
+            ),
+            field_series("token_nums", |r| r.token_number.into()),
+            address_series("transmitters", |r| r.transmitter),
+            address_series("receivers", |r| r.receiver),
+            field_series("sequence_nums", |r| r.sequence_number.into()),
+            field_series("ru_start_index", |r| r.ru_start_index.into()),
+            field_series("ru_end_index", |r| r.ru_end_index.into()),
+            cqi_series,
+            Series::new(
+                "ndpa_timestamps",
+                &reports
+                    .iter()
+                    .map(|r| r.sounding.map(|s| s.ndpa_timestamp))
+                    .collect::<Vec<Option<f64>>>(),
+            ),
+            Series::new(
+                "beamformers",
+                reports
+                    .iter()
+                    .map(|r| r.sounding.map(|s| s.beamformer.to_string()))
+                    .collect::<Vec<Option<String>>>(),
+            ),
+            Series::new(
+                "fcs_valid",
+                &reports.iter().map(|r| r.fcs_valid).collect::<Vec<_>>(),
+            ),
+        ])?;
+
+        let file = File::create(file_path)?;
+        ParquetWriter::new(file).finish(&mut df)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cqi_reports_in_sibling_file() {
+        let mut data = ExtractedBfiData::new();
+        data.cqi_reports.push(CqiReport {
+            timestamp: 1.5,
+            token_number: 7,
+            transmitter: MacAddress([1; 6]),
+            receiver: MacAddress([0xaa; 6]),
+            sequence_number: 12,
+            ru_start_index: 0,
+            ru_end_index: 1,
+            cqi: vec![vec![3, 4], vec![5, 6]],
+            sounding: None,
+            fcs_valid: Some(true),
+        });
+
+        let path = std::env::temp_dir().join(format!("bfi_cqi_{}.parquet", std::process::id()));
+        data.to_parquet(path.clone()).unwrap();
+        let cqi_path = cqi_parquet_path(&path);
+        let df = ParquetReader::new(File::open(&cqi_path).unwrap())
+            .finish()
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(cqi_path).unwrap();
+
+        assert_eq!(df.height(), 1);
+        assert_eq!(
+            df.column("token_nums").unwrap().u32().unwrap().get(0),
+            Some(7)
+        );
+        assert_eq!(
+            df.column("transmitters").unwrap().str().unwrap().get(0),
+            Some("01:01:01:01:01:01")
+        );
+        let cqi = df
+            .column("cqi")
+            .unwrap()
+            .list()
+            .unwrap()
+            .get_as_series(0)
+            .unwrap();
+        let second_ru = cqi.list().unwrap().get_as_series(1).unwrap();
+        assert_eq!(second_ru.u32().unwrap().to_vec(), vec![Some(5), Some(6)]);
+        assert_eq!(df.column("beamformers").unwrap().null_count(), 1);
+    }
 }
@@ -0,0 +1,358 @@
+/** ------------------------------------------------------------
+ * Radiotap header parsing.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::util::align_up;
+use bilge::prelude::*;
+
+/**
+ * Bits in a present word that do not denote a field, but control the
+ * interpretation of the following present words.
+ */
+const RADIOTAP_NAMESPACE_BIT: u32 = 29;
+const VENDOR_NAMESPACE_BIT: u32 = 30;
+const EXT_BIT: u32 = 31;
+
+/**
+ * Length of the fixed part of the header (version, pad, length, present)
+ */
+const FIXED_HEADER_LENGTH: usize = 8;
+
+/**
+ * Radiotap fields with a defined meaning in the default namespace
+ */
+const FIELD_TSFT: u32 = 0;
+const FIELD_FLAGS: u32 = 1;
+const FIELD_RATE: u32 = 2;
+const FIELD_CHANNEL: u32 = 3;
+const FIELD_ANTENNA_SIGNAL: u32 = 5;
+const FIELD_ANTENNA_NOISE: u32 = 6;
+const FIELD_MCS: u32 = 19;
+const FIELD_VHT: u32 = 21;
+const FIELD_HE: u32 = 23;
+
+/**
+ * (alignment, size) in bytes of every field in the radiotap namespace,
+ * indexed by its bit in the present word. The TLV field (bit 28) has no
+ * fixed size and ends the walk, as does any field not in this table.
+ * See https://www.radiotap.org/fields/defined
+ */
+#[rustfmt::skip]
+const FIELD_LAYOUT: &[(usize, usize)] = &[
+    (8, 8),  //  0: TSFT
+    (1, 1),  //  1: Flags
+    (1, 1),  //  2: Rate
+    (2, 4),  //  3: Channel
+    (2, 2),  //  4: FHSS
+    (1, 1),  //  5: Antenna signal (dBm)
+    (1, 1),  //  6: Antenna noise (dBm)
+    (2, 2),  //  7: Lock quality
+    (2, 2),  //  8: TX attenuation
+    (2, 2),  //  9: dB TX attenuation
+    (1, 1),  // 10: dBm TX power
+    (1, 1),  // 11: Antenna
+    (1, 1),  // 12: dB antenna signal
+    (1, 1),  // 13: dB antenna noise
+    (2, 2),  // 14: RX flags
+    (2, 2),  // 15: TX flags
+    (1, 1),  // 16: RTS retries
+    (1, 1),  // 17: Data retries
+    (4, 8),  // 18: XChannel
+    (1, 3),  // 19: MCS
+    (4, 8),  // 20: A-MPDU status
+    (2, 12), // 21: VHT
+    (8, 12), // 22: Timestamp
+    (2, 12), // 23: HE
+    (2, 12), // 24: HE-MU
+    (2, 6),  // 25: HE-MU-other-user
+    (1, 1),  // 26: 0-length PSDU
+    (2, 4),  // 27: L-SIG
+];
+
+/**
+ * Radiotap flags field (bit 1)
+ */
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+pub struct RadiotapFlags {
+    pub cfp: bool,
+    pub short_preamble: bool,
+    pub wep: bool,
+    pub fragmentation: bool,
+    pub fcs_at_end: bool,
+    pub data_pad: bool,
+    pub bad_fcs: bool,
+    pub short_gi: bool,
+}
+
+/**
+ * Decoded radiotap header. Fields that are not present in the
+ * capture are left as `None`.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RadiotapHeader {
+    pub version: u8,
+    pub length: usize,                // Length of the header in bytes
+    pub tsft: Option<u64>,            // MAC timestamp in microseconds
+    pub flags: Option<RadiotapFlags>, // Frame properties
+    pub rate: Option<u8>,             // Legacy data rate in 500 kbps units
+    pub channel_freq: Option<u16>,    // Channel center frequency in MHz
+    pub channel_flags: Option<u16>,   // Channel properties
+    pub antenna_signal: Option<i8>,   // RF signal power in dBm
+    pub antenna_noise: Option<i8>,    // RF noise power in dBm
+    pub mcs: Option<u8>,              // MCS index from the HT, VHT or HE field
+}
+
+/**
+ * Namespace the current present word belongs to
+ */
+enum Namespace {
+    Radiotap,
+    Vendor,
+}
+
+impl RadiotapHeader {
+    /**
+     * Parse the radiotap header at the start of the buffer.
+     *
+     * Walks all present bitmaps (including extended ones) and picks up the
+     * fields of interest from the first radiotap namespace. Later radiotap
+     * namespaces usually carry per-chain values and are skipped, as is any
+     * vendor namespace data.
+     */
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < FIXED_HEADER_LENGTH {
+            return Err(BfaExtractionError::TruncatedRadiotapHeader {
+                required: FIXED_HEADER_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        let version = buf[0];
+        if version != 0 {
+            return Err(BfaExtractionError::UnsupportedRadiotapVersion { version });
+        }
+
+        let length = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+        if length < FIXED_HEADER_LENGTH || length > buf.len() {
+            return Err(BfaExtractionError::TruncatedRadiotapHeader {
+                required: length.max(FIXED_HEADER_LENGTH),
+                available: buf.len(),
+            });
+        }
+        let header = &buf[..length];
+
+        // Collect all present words, each one with the ext bit set is
+        // followed by another one.
+        let mut present_words = Vec::new();
+        let mut offset = 4;
+        loop {
+            if offset + 4 > length {
+                return Err(BfaExtractionError::TruncatedRadiotapHeader {
+                    required: offset + 4,
+                    available: length,
+                });
+            }
+            let word = u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ]);
+            present_words.push(word);
+            offset += 4;
+
+            if word & (1 << EXT_BIT) == 0 {
+                break;
+            }
+        }
+
+        let mut radiotap = RadiotapHeader {
+            version,
+            length,
+            ..Default::default()
+        };
+
+        // Walk over the data fields in order of the present bits. Every field
+        // is aligned to its natural alignment relative to the header start.
+        let mut namespace = Namespace::Radiotap;
+        let mut namespace_word = 0; // Index of present word within namespace
+        let mut first_namespace = true;
+
+        'walk: for word in present_words {
+            if let Namespace::Radiotap = namespace {
+                for bit in 0..RADIOTAP_NAMESPACE_BIT {
+                    if word & (1 << bit) == 0 {
+                        continue;
+                    }
+
+                    // Fields past an unknown one can not be located anymore
+                    let field = bit + 32 * namespace_word;
+                    let Some(&(align, size)) = FIELD_LAYOUT.get(field as usize) else {
+                        break 'walk;
+                    };
+
+                    offset = align_up(offset, align);
+                    if offset + size > length {
+                        return Err(BfaExtractionError::TruncatedRadiotapHeader {
+                            required: offset + size,
+                            available: length,
+                        });
+                    }
+
+                    if first_namespace {
+                        radiotap.store_field(field, &header[offset..offset + size]);
+                    }
+                    offset += size;
+                }
+            }
+
+            if word & (1 << RADIOTAP_NAMESPACE_BIT) != 0 {
+                namespace = Namespace::Radiotap;
+                namespace_word = 0;
+                first_namespace = false;
+            } else if word & (1 << VENDOR_NAMESPACE_BIT) != 0 {
+                // Vendor namespace data starts with OUI (3), sub namespace (1)
+                // and skip length (2) which tells us how much data follows.
+                offset = align_up(offset, 2);
+                if offset + 6 > length {
+                    return Err(BfaExtractionError::TruncatedRadiotapHeader {
+                        required: offset + 6,
+                        available: length,
+                    });
+                }
+                let skip_length =
+                    u16::from_le_bytes([header[offset + 4], header[offset + 5]]) as usize;
+                offset += 6 + skip_length;
+
+                namespace = Namespace::Vendor;
+                namespace_word = 0;
+                first_namespace = false;
+            } else {
+                namespace_word += 1;
+            }
+        }
+
+        Ok(radiotap)
+    }
+
+    /**
+     * Store the value of a single field from its raw bytes
+     */
+    fn store_field(&mut self, field: u32, data: &[u8]) {
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+
+        match field {
+            FIELD_TSFT => {
+                let mut tsft = [0u8; 8];
+                tsft.copy_from_slice(data);
+                self.tsft = Some(u64::from_le_bytes(tsft));
+            }
+            FIELD_FLAGS => self.flags = Some(RadiotapFlags::from(data[0])),
+            FIELD_RATE => self.rate = Some(data[0]),
+            FIELD_CHANNEL => {
+                self.channel_freq = Some(u16_at(0));
+                self.channel_flags = Some(u16_at(2));
+            }
+            FIELD_ANTENNA_SIGNAL => self.antenna_signal = Some(data[0] as i8),
+            FIELD_ANTENNA_NOISE => self.antenna_noise = Some(data[0] as i8),
+            FIELD_MCS => {
+                // known (1), flags (1), mcs (1)
+                const MCS_INDEX_KNOWN: u8 = 0x02;
+                if data[0] & MCS_INDEX_KNOWN != 0 {
+                    self.mcs = Some(data[2]);
+                }
+            }
+            FIELD_VHT => {
+                // known (2), flags (1), bandwidth (1), mcs_nss (4), ...
+                // MCS of the first user is in the upper nibble.
+                self.mcs = Some(data[4] >> 4);
+            }
+            FIELD_HE => {
+                // data1 .. data6 (2 each), MCS is in data3 bits 8-11
+                const DATA_MCS_KNOWN: u16 = 0x0020;
+                if u16_at(0) & DATA_MCS_KNOWN != 0 {
+                    self.mcs = Some(((u16_at(4) >> 8) & 0x0f) as u8);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radiotap_basic_fields() {
+        // present: flags, rate, channel, antenna signal, antenna noise
+        #[rustfmt::skip]
+        let byte_stream: &[u8] = &[
+            0x00, 0x00, 0x10, 0x00,     // version, pad, length 16
+            0x6e, 0x00, 0x00, 0x00,     // present
+            0x10,                       // flags: FCS at end
+            0x0c,                       // rate: 6 Mbps
+            0x9e, 0x16, 0x40, 0x01,     // channel: 5790 MHz, flags 0x0140
+            0xb8,                       // antenna signal -72 dBm
+            0xa1,                       // antenna noise -95 dBm
+            0xff, 0xff,                 // start of 802.11 frame
+        ];
+
+        let result = RadiotapHeader::from_buf(byte_stream).unwrap();
+        assert_eq!(result.length, 16);
+        assert_eq!(result.tsft, None);
+        assert!(result.flags.unwrap().fcs_at_end());
+        assert!(!result.flags.unwrap().bad_fcs());
+        assert_eq!(result.rate, Some(12));
+        assert_eq!(result.channel_freq, Some(5790));
+        assert_eq!(result.channel_flags, Some(0x0140));
+        assert_eq!(result.antenna_signal, Some(-72));
+        assert_eq!(result.antenna_noise, Some(-95));
+        assert_eq!(result.mcs, None);
+    }
+
+    #[test]
+    fn radiotap_extended_bitmaps_and_alignment() {
+        // Layout similar to mac80211: two radiotap namespaces with per-chain
+        // signal in the second one, TSFT aligned to 8 bytes after 3 words.
+        #[rustfmt::skip]
+        let byte_stream: &[u8] = &[
+            0x00, 0x00, 0x28, 0x00,     // version, pad, length 40
+            0x23, 0x00, 0x80, 0xa0,     // present: tsft, flags, antsignal, he, ns, ext
+            0x20, 0x08, 0x00, 0x00,     // present: antsignal, antenna
+            0x00, 0x00, 0x00, 0x00,     // padding to 8 byte alignment
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // tsft
+            0x00,                       // flags
+            0xc4,                       // antenna signal -60 dBm
+            0x20, 0x00,                 // HE data1: data MCS known
+            0x00, 0x00,                 // HE data2
+            0x00, 0x07,                 // HE data3: MCS 7
+            0x00, 0x00, 0x00, 0x00,     // HE data4, data5
+            0x00, 0x00,                 // HE data6
+            0xc2, 0x00,                 // antenna signal chain 0 -62 dBm, antenna 0
+        ];
+
+        let result = RadiotapHeader::from_buf(byte_stream).unwrap();
+        assert_eq!(result.tsft, Some(0x0807060504030201));
+        assert_eq!(result.antenna_signal, Some(-60));
+        assert_eq!(result.mcs, Some(7));
+        assert_eq!(result.length, 40);
+
+        // Header too short for the announced HE field
+        assert!(matches!(
+            RadiotapHeader::from_buf(&byte_stream[..30]),
+            Err(BfaExtractionError::TruncatedRadiotapHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn radiotap_invalid_version() {
+        let byte_stream: &[u8] = &[0x01, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let result = RadiotapHeader::from_buf(byte_stream);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::UnsupportedRadiotapVersion { version: 1 })
+        ));
+    }
+}
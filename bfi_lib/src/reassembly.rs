@@ -0,0 +1,253 @@
+/** ------------------------------------------------------------
+ * Reassembly of feedback reports split across several frames.
+ * ------------------------------------------------------------- */
+use crate::fcs::combine_validity;
+use crate::mac_header::MacAddress;
+use crate::FeedbackFrame;
+use std::collections::{BTreeMap, HashMap};
+
+/**
+ * Segments received for one report, keyed by their remaining feedback
+ * segments count
+ */
+#[derive(Default)]
+struct SegmentSet {
+    segments: BTreeMap<u8, FeedbackFrame>,
+    num_segments: Option<usize>, // Known once the first segment arrived
+    out_of_order: bool,
+}
+
+impl SegmentSet {
+    /**
+     * Whether a segment fits into this set. Otherwise, it belongs to a
+     * later sounding reusing the dialog token.
+     */
+    fn accepts(&self, remaining: u8, first: bool) -> bool {
+        if self.segments.contains_key(&remaining) || (first && self.num_segments.is_some()) {
+            return false;
+        }
+
+        let num_segments = match first {
+            true => Some(remaining as usize + 1),
+            false => self.num_segments,
+        };
+        num_segments.is_none_or(|num| {
+            (remaining as usize) < num && self.segments.keys().all(|&r| (r as usize) < num)
+        })
+    }
+
+    fn is_complete(&self) -> bool {
+        self.num_segments == Some(self.segments.len())
+    }
+}
+
+/**
+ * Reassembles reports split across several frames
+ *
+ * Segments are matched by transmitter and sounding dialog token, or the
+ * sounding timestamp for HT. The first segment announces the number of
+ * remaining segments, which counts down to 0 in the last segment.
+ * Unsegmented frames are the first and last segment at once. Segments
+ * may arrive in any order, their payloads are concatenated in segment
+ * order. HT segments lack the first segment flag, so the first one to
+ * arrive is taken as the first segment.
+ */
+#[derive(Default)]
+pub struct SegmentReassembler {
+    pending: HashMap<(MacAddress, u32), SegmentSet>,
+    pub reassembled: usize,  // Reports made up of several segments
+    pub incomplete: usize,   // Segment sets with missing segments
+    pub out_of_order: usize, // Segment sets received out of order
+}
+
+impl SegmentReassembler {
+    /**
+     * Add a feedback frame, returning the complete report once all of
+     * its segments are present
+     */
+    pub fn push(&mut self, frame: FeedbackFrame) -> Option<FeedbackFrame> {
+        let remaining = frame.mimo_control.remaining_feedback_segments();
+        let key = (
+            frame.mac_header.transmitter,
+            frame.mimo_control.segment_set_id(),
+        );
+
+        // HT has no first segment flag, but sends its segments in order
+        let first = frame
+            .mimo_control
+            .first_feedback_segment()
+            .unwrap_or(!self.pending.contains_key(&key));
+        if first && remaining == 0 {
+            return Some(frame);
+        }
+
+        let set = self.pending.entry(key).or_default();
+        if !set.accepts(remaining, first) {
+            let stale = std::mem::take(set);
+            self.incomplete += 1;
+            self.out_of_order += stale.out_of_order as usize;
+        }
+
+        // In order, every segment counts down from the previous one
+        if set.segments.keys().next().is_some_and(|&r| remaining > r) {
+            set.out_of_order = true;
+        }
+        if first {
+            set.num_segments = Some(remaining as usize + 1);
+        }
+        set.segments.insert(remaining, frame);
+
+        if !set.is_complete() {
+            return None;
+        }
+
+        let set = self.pending.remove(&key)?;
+        self.reassembled += 1;
+        self.out_of_order += set.out_of_order as usize;
+
+        // The first segment has the highest remaining count
+        let mut segments = set.segments.into_values().rev();
+        let mut report = segments.next()?;
+        for segment in segments {
+            report.payload.extend(segment.payload);
+            report.fcs_valid = combine_validity(report.fcs_valid, segment.fcs_valid);
+        }
+        Some(report)
+    }
+
+    /**
+     * Count the segment sets still pending at the end of a capture
+     */
+    pub fn finish(&mut self) {
+        for (_, set) in self.pending.drain() {
+            self.incomplete += 1;
+            self.out_of_order += set.out_of_order as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::he_mimo_ctrl::HeMimoControl;
+    use crate::ht_mimo_ctrl::HtMimoControl;
+    use crate::mac_header::MacHeader;
+    use crate::mimo_ctrl::MimoControl;
+    use crate::radiotap::RadiotapHeader;
+
+    fn segment(transmitter: u8, token: u8, remaining: u8, first: bool) -> FeedbackFrame {
+        let mut mac_bytes = [0u8; 24];
+        mac_bytes[0] = 0xe0;
+        mac_bytes[10] = transmitter;
+
+        // Nc index 1, Nr index 3, BW 20, RU 0 to 8
+        let segment_bits = (remaining << 4) | ((first as u8) << 7);
+        let mimo_bytes = [
+            0b00011001,
+            segment_bits,
+            0,
+            0b00000100 | ((token & 0b11) << 6),
+            token >> 2,
+        ];
+
+        FeedbackFrame {
+            timestamp: remaining as f64,
+            radiotap: RadiotapHeader::default(),
+            mac_header: MacHeader::from_buf(&mac_bytes).unwrap(),
+            mimo_control: MimoControl::He(HeMimoControl::from_buf(&mimo_bytes).unwrap()),
+            payload: vec![remaining],
+            fcs_valid: Some(true),
+        }
+    }
+
+    #[test]
+    fn unsegmented_report() {
+        let mut reassembler = SegmentReassembler::default();
+        let report = reassembler.push(segment(1, 5, 0, true)).unwrap();
+        assert_eq!(report.payload, vec![0]);
+        assert_eq!(reassembler.reassembled, 0);
+    }
+
+    #[test]
+    fn segments_in_order() {
+        let mut reassembler = SegmentReassembler::default();
+        assert!(reassembler.push(segment(1, 5, 2, true)).is_none());
+        assert!(reassembler.push(segment(2, 5, 0, true)).is_some());
+        assert!(reassembler.push(segment(1, 5, 1, false)).is_none());
+
+        let report = reassembler.push(segment(1, 5, 0, false)).unwrap();
+        assert_eq!(report.payload, vec![2, 1, 0]);
+        assert_eq!(report.timestamp, 2.0);
+        assert_eq!(report.mimo_control.dialog_token_number(), 5);
+
+        reassembler.finish();
+        assert_eq!(reassembler.reassembled, 1);
+        assert_eq!(reassembler.incomplete, 0);
+        assert_eq!(reassembler.out_of_order, 0);
+    }
+
+    #[test]
+    fn segments_out_of_order() {
+        let mut reassembler = SegmentReassembler::default();
+        assert!(reassembler.push(segment(1, 5, 0, false)).is_none());
+        assert!(reassembler.push(segment(1, 5, 2, true)).is_none());
+
+        let report = reassembler.push(segment(1, 5, 1, false)).unwrap();
+        assert_eq!(report.payload, vec![2, 1, 0]);
+        assert_eq!(reassembler.reassembled, 1);
+        assert_eq!(reassembler.out_of_order, 1);
+    }
+
+    #[test]
+    fn incomplete_segments() {
+        let mut reassembler = SegmentReassembler::default();
+
+        // Missing middle segment, superseded by the next sounding
+        assert!(reassembler.push(segment(1, 5, 2, true)).is_none());
+        assert!(reassembler.push(segment(1, 5, 0, false)).is_none());
+        assert!(reassembler.push(segment(1, 5, 1, true)).is_none());
+        assert_eq!(reassembler.incomplete, 1);
+
+        let report = reassembler.push(segment(1, 5, 0, false)).unwrap();
+        assert_eq!(report.payload, vec![1, 0]);
+
+        // Missing first segment at the end of the capture
+        assert!(reassembler.push(segment(1, 6, 0, false)).is_none());
+        reassembler.finish();
+        assert_eq!(reassembler.incomplete, 2);
+        assert_eq!(reassembler.reassembled, 1);
+    }
+
+    #[test]
+    fn ht_segments() {
+        let mut reassembler = SegmentReassembler::default();
+        let ht_segment = |remaining: u8, sounding_timestamp: u8| {
+            let mut frame = segment(1, 0, remaining, false);
+            let mimo_bytes = [0x1d, remaining << 3, sounding_timestamp, 0, 0, 0];
+            frame.mimo_control = MimoControl::Ht(HtMimoControl::from_buf(&mimo_bytes).unwrap());
+            frame
+        };
+
+        // Two segments of one sounding, then an unsegmented report
+        assert!(reassembler.push(ht_segment(1, 7)).is_none());
+        let report = reassembler.push(ht_segment(0, 7)).unwrap();
+        assert_eq!(report.payload, vec![1, 0]);
+        assert!(reassembler.push(ht_segment(0, 8)).is_some());
+
+        // The first segment of a later sounding does not reset the set
+        assert!(reassembler.push(ht_segment(1, 9)).is_none());
+        assert!(reassembler.push(ht_segment(1, 10)).is_none());
+        assert_eq!(
+            reassembler.push(ht_segment(0, 9)).unwrap().payload,
+            vec![1, 0]
+        );
+        assert_eq!(
+            reassembler.push(ht_segment(0, 10)).unwrap().payload,
+            vec![1, 0]
+        );
+
+        reassembler.finish();
+        assert_eq!(reassembler.reassembled, 3);
+        assert_eq!(reassembler.incomplete, 0);
+    }
+}
@@ -0,0 +1,172 @@
+/** ------------------------------------------------------------
+ * Extraction report summarizing the packets of a capture.
+ * ------------------------------------------------------------- */
+use crate::action_frame::SkipReason;
+use crate::errors::BfaExtractionError;
+use crate::mac_header::MacAddress;
+use crate::sequence::SequenceStats;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/**
+ * Failures of one error kind
+ */
+#[derive(Debug, Default, Clone)]
+pub struct FailureStats {
+    pub count: usize,
+    pub first_indices: Vec<usize>, // Packet indices of the first failures
+}
+
+/**
+ * Summary of an extraction run over a capture
+ */
+#[derive(Debug, Default, Clone)]
+pub struct ExtractionReport {
+    pub total_packets: usize,
+    pub extracted: usize,
+    pub extracted_cqi: usize,                           // CQI-only reports
+    pub reassembled: usize,                             // Reports made up of several segments
+    pub incomplete_segment_sets: usize,                 // Segmented reports missing segments
+    pub out_of_order_segment_sets: usize,               // Segmented reports received out of order
+    pub ndp_announcements: usize,                       // NDPAs announcing a sounding
+    pub mismatched_reports: usize,                      // Reports differing from their NDPA request
+    pub bad_fcs_reports: usize,                         // Reports kept despite a bad FCS
+    pub skipped_frames: BTreeMap<SkipReason, usize>,    // Frames which are no BFI reports
+    pub failures: BTreeMap<&'static str, FailureStats>, // Malformed packets per error kind
+    // Sounding sequence statistics per beamformer
+    pub sounding_stats: BTreeMap<MacAddress, SequenceStats>,
+}
+
+impl ExtractionReport {
+    pub fn record_skip(&mut self, reason: SkipReason) {
+        *self.skipped_frames.entry(reason).or_insert(0) += 1;
+    }
+
+    /**
+     * Count a failed packet, remembering at most max_indices packet
+     * indices per error kind.
+     */
+    pub fn record_failure(&mut self, index: usize, error: &BfaExtractionError, max_indices: usize) {
+        let stats = self.failures.entry(error.kind()).or_default();
+        stats.count += 1;
+        if stats.first_indices.len() < max_indices {
+            stats.first_indices.push(index);
+        }
+    }
+
+    pub fn num_skipped(&self) -> usize {
+        self.skipped_frames.values().sum()
+    }
+
+    pub fn num_failed(&self) -> usize {
+        self.failures.values().map(|stats| stats.count).sum()
+    }
+}
+
+impl fmt::Display for ExtractionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Processed {} packets, extracted {} reports",
+            self.total_packets, self.extracted
+        )?;
+
+        if self.extracted_cqi > 0 {
+            writeln!(f, "Extracted {} CQI reports", self.extracted_cqi)?;
+        }
+
+        if self.reassembled > 0 {
+            writeln!(f, "Reassembled {} segmented reports", self.reassembled)?;
+        }
+        if self.incomplete_segment_sets > 0 {
+            writeln!(
+                f,
+                "Dropped {} incomplete segmented reports",
+                self.incomplete_segment_sets
+            )?;
+        }
+        if self.out_of_order_segment_sets > 0 {
+            writeln!(
+                f,
+                "Received {} segmented reports out of order",
+                self.out_of_order_segment_sets
+            )?;
+        }
+
+        if self.ndp_announcements > 0 {
+            writeln!(f, "Found {} NDP Announcements", self.ndp_announcements)?;
+        }
+        if self.mismatched_reports > 0 {
+            writeln!(
+                f,
+                "{} reports differ from the feedback requested in their NDPA",
+                self.mismatched_reports
+            )?;
+        }
+        if self.bad_fcs_reports > 0 {
+            writeln!(f, "Kept {} reports with a bad FCS", self.bad_fcs_reports)?;
+        }
+
+        for (beamformer, stats) in &self.sounding_stats {
+            writeln!(
+                f,
+                "Beamformer {}: {} soundings, {} missing, {} duplicate and {} reordered reports",
+                beamformer, stats.soundings, stats.missing, stats.duplicates, stats.reordered
+            )?;
+        }
+
+        for (reason, count) in &self.skipped_frames {
+            writeln!(f, "Skipped {} frames: {}", count, reason)?;
+        }
+
+        for (kind, stats) in &self.failures {
+            let indices: Vec<String> = stats.first_indices.iter().map(usize::to_string).collect();
+            writeln!(
+                f,
+                "Failed {} packets: {} (first at packet {})",
+                stats.count,
+                kind,
+                indices.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_failure_bookkeeping() {
+        let mut report = ExtractionReport::default();
+        let error = BfaExtractionError::TruncatedFrame {
+            required: 10,
+            available: 5,
+        };
+
+        for index in [3, 5, 8, 13] {
+            report.record_failure(index, &error, 2);
+        }
+        report.record_failure(
+            21,
+            &BfaExtractionError::UnsupportedDimensions {
+                nr_index: 0,
+                nc_index: 0,
+            },
+            2,
+        );
+        report.record_skip(SkipReason::UnsupportedCategory { category: 21 });
+
+        let stats = &report.failures["TruncatedFrame"];
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.first_indices, vec![3, 5]);
+        assert_eq!(
+            report.failures["UnsupportedDimensions"].first_indices,
+            vec![21]
+        );
+        assert_eq!(report.num_failed(), 5);
+        assert_eq!(report.num_skipped(), 1);
+    }
+}
@@ -0,0 +1,214 @@
+/** ------------------------------------------------------------
+ * Unwrapping of the 6 bit dialog token into a sounding sequence.
+ * ------------------------------------------------------------- */
+use crate::action_frame::PhyGeneration;
+use crate::bfi_data::ExtractedBfiData;
+use crate::mac_header::MacAddress;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/**
+ * Number of distinct dialog tokens before they wrap around
+ */
+const TOKEN_RANGE: i64 = 64;
+
+/**
+ * Weight of the latest sounding interval in the running estimate
+ */
+const INTERVAL_SMOOTHING: f64 = 0.2;
+
+/**
+ * Sounding statistics of one beamformer
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SequenceStats {
+    pub soundings: usize,  // Soundings with at least one report
+    pub missing: usize,    // Soundings in between without any report
+    pub duplicates: usize, // Repeated reports of a station for one sounding
+    pub reordered: usize,  // Reports arriving after those of a later sounding
+}
+
+/**
+ * Sounding sequence numbers of all reports, with statistics per beamformer
+ */
+#[derive(Debug, Default, Clone)]
+pub struct SoundingSequence {
+    pub unwrapped_tokens: Vec<Option<i64>>, // None for HT reports without dialog token
+    pub stats: BTreeMap<MacAddress, SequenceStats>,
+}
+
+/**
+ * Unwraps the dialog tokens of one beamformer
+ *
+ * Tokens normally count up by one per sounding. A token within half the
+ * token range before the latest one belongs to a late report. After a
+ * long pause, the number of wrap arounds is estimated from the elapsed
+ * time and the typical sounding interval.
+ */
+struct TokenUnwrapper {
+    latest_seq: i64,
+    latest_timestamp: f64,
+    interval: Option<f64>, // Running estimate of the time between soundings
+}
+
+impl TokenUnwrapper {
+    fn new(token: u8, timestamp: f64) -> Self {
+        TokenUnwrapper {
+            latest_seq: token as i64,
+            latest_timestamp: timestamp,
+            interval: None,
+        }
+    }
+
+    fn unwrap(&mut self, token: u8, timestamp: f64) -> i64 {
+        let delta = (token as i64 - self.latest_seq).rem_euclid(TOKEN_RANGE);
+        let elapsed = timestamp - self.latest_timestamp;
+        let expected_steps = self.interval.map_or(0.0, |interval| elapsed / interval);
+
+        let seq = if expected_steps >= (TOKEN_RANGE / 2) as f64 {
+            let wraps = ((expected_steps - delta as f64) / TOKEN_RANGE as f64).round();
+            self.latest_seq + delta + TOKEN_RANGE * wraps.max(0.0) as i64
+        } else if delta <= TOKEN_RANGE / 2 {
+            self.latest_seq + delta
+        } else {
+            self.latest_seq + delta - TOKEN_RANGE
+        };
+
+        if seq > self.latest_seq {
+            // Only unambiguous steps refine the interval estimate
+            let step_interval = elapsed / (seq - self.latest_seq) as f64;
+            if expected_steps < (TOKEN_RANGE / 2) as f64 && step_interval > 0.0 {
+                self.interval = Some(self.interval.map_or(step_interval, |interval| {
+                    interval + INTERVAL_SMOOTHING * (step_interval - interval)
+                }));
+            }
+            self.latest_seq = seq;
+            self.latest_timestamp = timestamp;
+        }
+        seq
+    }
+}
+
+impl ExtractedBfiData {
+    /**
+     * Unwrap the dialog tokens into a monotonic sounding sequence number
+     * per beamformer, and count missing soundings, duplicate and
+     * reordered reports. The sequence number of the first sounding of a
+     * beamformer is its dialog token. HT reports carry no dialog token and
+     * are left out.
+     */
+    pub fn sounding_sequence(&self) -> SoundingSequence {
+        let mut unwrappers: HashMap<MacAddress, TokenUnwrapper> = HashMap::new();
+        let mut seen: HashMap<MacAddress, BTreeSet<i64>> = HashMap::new();
+        let mut reports: HashSet<(MacAddress, MacAddress, i64)> = HashSet::new();
+        let mut sequence = SoundingSequence::default();
+
+        for (index, (&timestamp, &token)) in
+            self.timestamps.iter().zip(&self.token_nums).enumerate()
+        {
+            if self.phy_generations[index] == PhyGeneration::Ht {
+                sequence.unwrapped_tokens.push(None);
+                continue;
+            }
+
+            let beamformer = self.beamformer(index);
+            let unwrapper = unwrappers
+                .entry(beamformer)
+                .or_insert_with(|| TokenUnwrapper::new(token, timestamp));
+            let latest_seq = unwrapper.latest_seq;
+            let seq = unwrapper.unwrap(token, timestamp);
+
+            let stats = sequence.stats.entry(beamformer).or_default();
+            stats.reordered += (seq < latest_seq) as usize;
+            stats.duplicates +=
+                !reports.insert((beamformer, self.transmitters[index], seq)) as usize;
+            seen.entry(beamformer).or_default().insert(seq);
+            sequence.unwrapped_tokens.push(Some(seq));
+        }
+
+        for (beamformer, seqs) in seen {
+            let stats = sequence.stats.entry(beamformer).or_default();
+            let (first, last) = (seqs.first().unwrap(), seqs.last().unwrap());
+            stats.soundings = seqs.len();
+            stats.missing = (last - first + 1) as usize - seqs.len();
+        }
+
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bfi_data::SinglePacketBfiData;
+    use crate::test_util::report;
+
+    #[test]
+    fn unwrap_token_wrap_around() {
+        let mut data = ExtractedBfiData::new();
+        for (i, token) in (60..64).chain(0..3).enumerate() {
+            data.push(report(i as f64 * 0.01, token, 1));
+        }
+
+        let sequence = data.sounding_sequence();
+        assert_eq!(
+            sequence.unwrapped_tokens,
+            (60..67).map(Some).collect::<Vec<_>>()
+        );
+        assert_eq!(sequence.stats[&MacAddress([0xaa; 6])].missing, 0);
+    }
+
+    #[test]
+    fn unwrap_after_long_pause() {
+        let mut data = ExtractedBfiData::new();
+        data.push(report(0.00, 10, 1));
+        data.push(report(0.01, 11, 1));
+        data.push(report(0.02, 12, 1));
+        // 100 soundings later, token 112 wrapped to 48
+        data.push(report(1.02, 48, 1));
+
+        let sequence = data.sounding_sequence();
+        assert_eq!(
+            sequence.unwrapped_tokens,
+            vec![Some(10), Some(11), Some(12), Some(112)]
+        );
+        assert_eq!(sequence.stats[&MacAddress([0xaa; 6])].missing, 99);
+    }
+
+    #[test]
+    fn sequence_statistics() {
+        let mut data = ExtractedBfiData::new();
+        data.push(report(0.00, 62, 1));
+        data.push(report(0.00, 62, 2)); // other station, same sounding
+        data.push(report(0.01, 63, 1));
+        data.push(report(0.03, 1, 1)); // token 0 missing
+        data.push(report(0.03, 63, 2)); // late report
+        data.push(report(0.03, 1, 1)); // duplicate
+        data.push(SinglePacketBfiData {
+            phy_generation: PhyGeneration::Ht,
+            ..report(0.04, 0, 1) // no dialog token
+        });
+
+        let sequence = data.sounding_sequence();
+        assert_eq!(
+            sequence.unwrapped_tokens,
+            vec![
+                Some(62),
+                Some(62),
+                Some(63),
+                Some(65),
+                Some(63),
+                Some(65),
+                None
+            ]
+        );
+        assert_eq!(
+            sequence.stats[&MacAddress([0xaa; 6])],
+            SequenceStats {
+                soundings: 3,
+                missing: 1,
+                duplicates: 1,
+                reordered: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,128 @@
+/** ------------------------------------------------------------
+ * Grouping of extracted reports into sounding sessions.
+ * ------------------------------------------------------------- */
+use crate::bfi_data::ExtractedBfiData;
+use crate::mac_header::MacAddress;
+use std::collections::HashMap;
+
+/**
+ * Default time window of a sounding session in seconds
+ */
+pub const DEFAULT_SESSION_WINDOW: f64 = 0.1;
+
+/**
+ * Reports of all beamformees answering one sounding of a beamformer
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundingSession {
+    pub beamformer: MacAddress,
+    pub dialog_token_number: u8,
+    pub start_timestamp: f64,       // NDPA, or first report without NDPA
+    pub end_timestamp: f64,         // Last report
+    pub stations: Vec<MacAddress>,  // Beamformees in order of their first report
+    pub report_indices: Vec<usize>, // Indices into the extracted data
+}
+
+impl SoundingSession {
+    fn new(beamformer: MacAddress, dialog_token_number: u8, start_timestamp: f64) -> Self {
+        SoundingSession {
+            beamformer,
+            dialog_token_number,
+            start_timestamp,
+            end_timestamp: start_timestamp,
+            stations: Vec::new(),
+            report_indices: Vec::new(),
+        }
+    }
+
+    fn add_report(&mut self, index: usize, station: MacAddress, timestamp: f64) {
+        if !self.stations.contains(&station) {
+            self.stations.push(station);
+        }
+        self.report_indices.push(index);
+        self.end_timestamp = self.end_timestamp.max(timestamp);
+    }
+
+    /**
+     * Get the reports of this session
+     */
+    pub fn reports(&self, data: &ExtractedBfiData) -> ExtractedBfiData {
+        data.select(&self.report_indices)
+    }
+}
+
+impl ExtractedBfiData {
+    /**
+     * Group the reports into sounding sessions
+     *
+     * Reports belong to the same session if they are sent to the same
+     * beamformer with the same dialog token within window seconds after
+     * the start of the session. As the 6 bit dialog token wraps around
+     * after 64 soundings, a token reused after the window (or announced
+     * by a different NDPA) starts a new session.
+     *
+     * \returns The sessions ordered by their start
+     */
+    pub fn sounding_sessions(&self, window: f64) -> Vec<SoundingSession> {
+        let mut sessions: Vec<SoundingSession> = Vec::new();
+        let mut open: HashMap<(MacAddress, u8), usize> = HashMap::new();
+
+        for (index, &timestamp) in self.timestamps.iter().enumerate() {
+            let sounding = self.soundings.get(index).copied().flatten();
+            let key = (self.beamformer(index), self.token_nums[index]);
+            let start = sounding.map_or(timestamp, |s| s.ndpa_timestamp);
+
+            // A newer NDPA with the same token starts a new session as well
+            let open_session = open.get(&key).copied().filter(|&i| {
+                let session = &sessions[i];
+                sounding.is_none_or(|s| s.ndpa_timestamp <= session.start_timestamp)
+                    && timestamp - session.start_timestamp <= window
+            });
+
+            let session_index = open_session.unwrap_or_else(|| {
+                sessions.push(SoundingSession::new(key.0, key.1, start));
+                open.insert(key, sessions.len() - 1);
+                sessions.len() - 1
+            });
+            sessions[session_index].add_report(index, self.transmitters[index], timestamp);
+        }
+
+        sessions.sort_by(|a, b| a.start_timestamp.total_cmp(&b.start_timestamp));
+        sessions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::report;
+
+    #[test]
+    fn group_sounding_sessions() {
+        let mut data = ExtractedBfiData::new();
+        data.push(report(0.000, 10, 1));
+        data.push(report(0.002, 10, 2));
+        data.push(report(0.010, 11, 1));
+        data.push(report(0.011, 10, 3)); // late answer to token 10
+        data.push(report(5.000, 10, 1)); // token 10 reused after wrapping
+
+        let sessions = data.sounding_sessions(DEFAULT_SESSION_WINDOW);
+        assert_eq!(sessions.len(), 3);
+
+        assert_eq!(sessions[0].dialog_token_number, 10);
+        assert_eq!(sessions[0].report_indices, vec![0, 1, 3]);
+        assert_eq!(
+            sessions[0].stations,
+            vec![MacAddress([1; 6]), MacAddress([2; 6]), MacAddress([3; 6])]
+        );
+        assert_eq!(sessions[0].end_timestamp, 0.011);
+
+        assert_eq!(sessions[1].dialog_token_number, 11);
+        assert_eq!(sessions[2].report_indices, vec![4]);
+        assert_eq!(sessions[2].start_timestamp, 5.0);
+
+        let reports = sessions[0].reports(&data);
+        assert_eq!(reports.timestamps, vec![0.000, 0.002, 0.011]);
+        assert_eq!(reports.transmitters[2], MacAddress([3; 6]));
+    }
+}
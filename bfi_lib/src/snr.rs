@@ -0,0 +1,149 @@
+/** ------------------------------------------------------------
+ * SNR fields of the HE compressed beamforming report.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::util::nibbles;
+
+/**
+ * Convert an average SNR field to dB
+ *
+ * The field is a two's complement value in 0.25 dB steps, where -128
+ * stands for -10 dB (or less) and 127 for 53.75 dB (or more).
+ * See IEEE 802.11ax Table 9-91f.
+ */
+pub fn snr_to_db(value: i8) -> f32 {
+    value as f32 / 4.0 + 22.0
+}
+
+/**
+ * Convert an SNR in dB to an average SNR field, the inverse of
+ * snr_to_db. SNRs outside the range of the field saturate.
+ */
+pub fn db_to_snr(db: f32) -> i8 {
+    ((db - 22.0) * 4.0).round().clamp(-128.0, 127.0) as i8
+}
+
+/**
+ * Extract the average SNR of every space-time stream (column) in dB
+ * from the start of the beamforming report
+ */
+pub fn extract_stream_snr(buf: &[u8], nc: usize) -> Result<Vec<f32>, BfaExtractionError> {
+    if buf.len() < nc {
+        return Err(BfaExtractionError::TruncatedFrame {
+            required: nc,
+            available: buf.len(),
+        });
+    }
+
+    Ok(buf[..nc]
+        .iter()
+        .map(|&value| snr_to_db(value as i8))
+        .collect())
+}
+
+/**
+ * Convert a 4 bit delta SNR field to dB
+ *
+ * The field is a two's complement value in 1 dB steps relative to the
+ * average SNR of the stream, covering -8 dB to 7 dB.
+ * See IEEE 802.11ax Table 9-91g.
+ */
+pub fn delta_snr_to_db(nibble: u8) -> i8 {
+    ((nibble << 4) as i8) >> 4
+}
+
+/**
+ * Convert a delta SNR in dB to a 4 bit field, the inverse of
+ * delta_snr_to_db. Deltas outside -8 dB to 7 dB saturate.
+ */
+pub fn db_to_delta_snr(db: i8) -> u8 {
+    db.clamp(-8, 7) as u8 & 0x0f
+}
+
+/**
+ * Extract the delta SNR of every column for every subcarrier from an
+ * HE MU Exclusive Beamforming Report
+ *
+ * The report holds a 4 bit value per column for each subcarrier of the
+ * compressed beamforming report, starting at the LSB of the first byte.
+ */
+pub fn extract_delta_snr(
+    buf: &[u8],
+    nc: usize,
+    num_subcarrier: usize,
+) -> Result<Vec<Vec<i8>>, BfaExtractionError> {
+    let required = (nc * num_subcarrier).div_ceil(2);
+    if buf.len() < required {
+        return Err(BfaExtractionError::TruncatedFrame {
+            required,
+            available: buf.len(),
+        });
+    }
+
+    let delta_snr: Vec<i8> = nibbles(&buf[..required]).map(delta_snr_to_db).collect();
+
+    Ok(delta_snr
+        .chunks_exact(nc)
+        .take(num_subcarrier)
+        .map(<[i8]>::to_vec)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snr_conversion() {
+        assert_eq!(snr_to_db(-128), -10.0);
+        assert_eq!(snr_to_db(-127), -9.75);
+        assert_eq!(snr_to_db(0), 22.0);
+        assert_eq!(snr_to_db(127), 53.75);
+
+        assert_eq!(db_to_snr(-9.75), -127);
+        assert_eq!(db_to_snr(22.0), 0);
+        assert_eq!(db_to_snr(-20.0), -128);
+        assert_eq!(db_to_snr(60.0), 127);
+    }
+
+    #[test]
+    fn stream_snr_extraction() {
+        let byte_stream: &[u8] = &[0x80, 0x00, 0x7f, 0xff];
+
+        let result = extract_stream_snr(byte_stream, 3).unwrap();
+        assert_eq!(result, vec![-10.0, 22.0, 53.75]);
+
+        let result = extract_stream_snr(byte_stream, 5);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 5,
+                available: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn delta_snr_extraction() {
+        assert_eq!(delta_snr_to_db(0x0), 0);
+        assert_eq!(delta_snr_to_db(0x7), 7);
+        assert_eq!(delta_snr_to_db(0x8), -8);
+        assert_eq!(delta_snr_to_db(0xf), -1);
+        assert_eq!(db_to_delta_snr(-1), 0xf);
+        assert_eq!(db_to_delta_snr(-9), 0x8);
+
+        // 3 subcarriers with 3 columns, the last nibble is padding
+        let byte_stream: &[u8] = &[0x10, 0x8f, 0x32, 0x54, 0x06];
+        let result = extract_delta_snr(byte_stream, 3, 3).unwrap();
+        assert_eq!(result, vec![vec![0, 1, -1], vec![-8, 2, 3], vec![4, 5, 6]]);
+
+        let result = extract_delta_snr(byte_stream, 4, 3);
+        assert!(matches!(
+            result,
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 6,
+                available: 5
+            })
+        ));
+    }
+}
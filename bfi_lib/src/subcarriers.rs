@@ -0,0 +1,422 @@
+/** ------------------------------------------------------------
+ * Subcarrier (tone) indices of compressed beamforming feedback.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::he_mimo_ctrl::Bandwidth;
+
+/**
+ * First and last tone of every 26-tone RU, indexed by the RU index used
+ * in the RU Start/End Index fields. The center RUs span the DC tones.
+ * See IEEE 802.11ax Table 27-7 to Table 27-9.
+ */
+#[rustfmt::skip]
+const RU26_TONES_20MHZ: &[(i16, i16)] = &[
+    (-121, -96), (-95, -70), (-68, -43), (-42, -17), (-16, 16),
+    (17, 42), (43, 68), (70, 95), (96, 121),
+];
+
+#[rustfmt::skip]
+const RU26_TONES_40MHZ: &[(i16, i16)] = &[
+    (-243, -218), (-217, -192), (-189, -164), (-163, -138), (-136, -111),
+    (-109, -84), (-83, -58), (-55, -30), (-29, -4), (4, 29), (30, 55),
+    (58, 83), (84, 109), (111, 136), (138, 163), (164, 189), (192, 217),
+    (218, 243),
+];
+
+#[rustfmt::skip]
+const RU26_TONES_80MHZ: &[(i16, i16)] = &[
+    (-499, -474), (-473, -448), (-445, -420), (-419, -394), (-392, -367),
+    (-365, -340), (-339, -314), (-311, -286), (-285, -260), (-257, -232),
+    (-231, -206), (-203, -178), (-177, -152), (-150, -125), (-123, -98),
+    (-97, -72), (-69, -44), (-43, -18), (-16, 16), (18, 43), (44, 69),
+    (72, 97), (98, 123), (125, 150), (152, 177), (178, 203), (206, 231),
+    (232, 257), (260, 285), (286, 311), (314, 339), (340, 365), (367, 392),
+    (394, 419), (420, 445), (448, 473), (474, 499),
+];
+
+/**
+ * 160 MHz consists of two 80 MHz segments, offset by this many tones
+ */
+const SEGMENT_OFFSET_160MHZ: i16 = 512;
+
+/**
+ * Get the tone range of a 26-tone RU for the given bandwidth
+ */
+fn ru26_tones(bandwidth: Bandwidth, ru_index: usize) -> Option<(i16, i16)> {
+    match bandwidth {
+        Bandwidth::Bw20 => RU26_TONES_20MHZ.get(ru_index).copied(),
+        Bandwidth::Bw40 => RU26_TONES_40MHZ.get(ru_index).copied(),
+        Bandwidth::Bw80 => RU26_TONES_80MHZ.get(ru_index).copied(),
+        Bandwidth::Bw160 => {
+            let num_ru = RU26_TONES_80MHZ.len();
+            let (segment_ru, offset) = match ru_index < num_ru {
+                true => (ru_index, -SEGMENT_OFFSET_160MHZ),
+                false => (ru_index - num_ru, SEGMENT_OFFSET_160MHZ),
+            };
+            RU26_TONES_80MHZ
+                .get(segment_ru)
+                .map(|&(first, last)| (first + offset, last + offset))
+        }
+        Bandwidth::Bw320 => None, // HE ends at 160 MHz
+    }
+}
+
+/**
+ * Number of 26-tone RUs in an HE bandwidth
+ */
+pub fn num_ru26(bandwidth: Bandwidth) -> usize {
+    match bandwidth {
+        Bandwidth::Bw20 => RU26_TONES_20MHZ.len(),
+        Bandwidth::Bw40 => RU26_TONES_40MHZ.len(),
+        Bandwidth::Bw80 => RU26_TONES_80MHZ.len(),
+        Bandwidth::Bw160 => 2 * RU26_TONES_80MHZ.len(),
+        Bandwidth::Bw320 => 0,
+    }
+}
+
+/**
+ * Offsets of the 80 MHz segments making up a bandwidth in tones
+ */
+fn segment_offsets(bandwidth: Bandwidth) -> &'static [i16] {
+    match bandwidth {
+        Bandwidth::Bw160 => &[-SEGMENT_OFFSET_160MHZ, SEGMENT_OFFSET_160MHZ],
+        Bandwidth::Bw320 => &[-1536, -512, 512, 1536],
+        _ => &[0],
+    }
+}
+
+/**
+ * Get the feedback tones of the full bandwidth for a grouping Ng of 4 or 16.
+ *
+ * The tones are spaced by Ng, starting next to the DC tones. In 20 MHz,
+ * this grid misses the outermost and the DC-adjacent tones, which are
+ * added explicitly. Wider bandwidths repeat the 80 MHz tones per segment.
+ */
+pub fn full_band_tones(bandwidth: Bandwidth, ng: usize) -> Vec<i16> {
+    let grid = |first: i16, last: i16| (first..=last).step_by(ng);
+
+    let positive: Vec<i16> = match bandwidth {
+        Bandwidth::Bw20 => std::iter::once(2)
+            .chain(grid(4, 120))
+            .chain(std::iter::once(122))
+            .collect(),
+        Bandwidth::Bw40 => grid(4, 244).collect(),
+        _ => grid(4, 500).collect(),
+    };
+
+    let segment: Vec<i16> = positive
+        .iter()
+        .rev()
+        .map(|&tone| -tone)
+        .chain(positive.iter().copied())
+        .collect();
+
+    segment_offsets(bandwidth)
+        .iter()
+        .flat_map(|&offset| segment.iter().map(move |&tone| tone + offset))
+        .collect()
+}
+
+/**
+ * Ensure the RU span is valid for the bandwidth, returning the number of
+ * 26-tone RUs it covers
+ */
+pub fn check_ru_span(
+    bandwidth: Bandwidth,
+    ru_start_index: u8,
+    ru_end_index: u8,
+) -> Result<usize, BfaExtractionError> {
+    if ru_start_index > ru_end_index || ru26_tones(bandwidth, ru_end_index as usize).is_none() {
+        return Err(BfaExtractionError::InvalidRuSpan {
+            ru_start_index,
+            ru_end_index,
+            bandwidth_mhz: bandwidth.to_mhz(),
+        });
+    }
+
+    Ok((ru_end_index - ru_start_index) as usize + 1)
+}
+
+/**
+ * Get the feedback tones for the RU span of a (partial bandwidth) report.
+ *
+ * These are the full band feedback tones covering the span from the
+ * first tone of the start RU to the last tone of the end RU, including
+ * the closest feedback tones enclosing that span.
+ */
+pub fn feedback_tones(
+    bandwidth: Bandwidth,
+    ng: usize,
+    ru_start_index: u8,
+    ru_end_index: u8,
+) -> Result<Vec<i16>, BfaExtractionError> {
+    check_ru_span(bandwidth, ru_start_index, ru_end_index)?;
+
+    // Both RUs exist after the check above
+    let (first, _) = ru26_tones(bandwidth, ru_start_index as usize).unwrap();
+    let (_, last) = ru26_tones(bandwidth, ru_end_index as usize).unwrap();
+
+    let tones = full_band_tones(bandwidth, ng);
+    let lower = tones.iter().rev().find(|&&tone| tone <= first);
+    let upper = tones.iter().find(|&&tone| tone >= last);
+    let lower = *lower.unwrap_or(&tones[0]);
+    let upper = *upper.unwrap_or(&tones[tones.len() - 1]);
+
+    Ok(tones
+        .into_iter()
+        .filter(|&tone| lower <= tone && tone <= upper)
+        .collect())
+}
+
+/**
+ * VHT 160 MHz consists of two 80 MHz segments, offset by this many tones
+ */
+const VHT_SEGMENT_OFFSET_160MHZ: i16 = 128;
+
+/**
+ * Get the feedback tones of VHT compressed beamforming for a grouping Ng
+ * of 1, 2 or 4. See IEEE 802.11ac Table 8-53g.
+ *
+ * The tones are spaced by Ng from the band edge towards DC. In 20 MHz,
+ * the DC-adjacent tones are added explicitly for Ng 2 and 4.
+ */
+pub fn vht_feedback_tones(bandwidth: Bandwidth, ng: usize) -> Vec<i16> {
+    let grid = |first: i16, last: i16| (first..=last).rev().step_by(ng).rev();
+
+    let positive: Vec<i16> = match bandwidth {
+        Bandwidth::Bw20 if ng > 1 => std::iter::once(1).chain(grid(2, 28)).collect(),
+        Bandwidth::Bw20 => grid(1, 28).collect(),
+        Bandwidth::Bw40 => grid(2, 58).collect(),
+        Bandwidth::Bw80 => grid(2, 122).collect(),
+        Bandwidth::Bw320 => return Vec::new(), // VHT ends at 160 MHz
+        Bandwidth::Bw160 => {
+            let segment = vht_feedback_tones(Bandwidth::Bw80, ng);
+            return segment
+                .iter()
+                .map(|&tone| tone - VHT_SEGMENT_OFFSET_160MHZ)
+                .chain(segment.iter().map(|&tone| tone + VHT_SEGMENT_OFFSET_160MHZ))
+                .collect();
+        }
+    };
+
+    positive
+        .iter()
+        .rev()
+        .map(|&tone| -tone)
+        .chain(positive.iter().copied())
+        .collect()
+}
+
+/**
+ * Get the feedback tones of HT compressed beamforming for a grouping Ng
+ * of 1, 2 or 4. See IEEE 802.11n Table 7-25f.
+ *
+ * 40 MHz uses the same tones as VHT. In 20 MHz with grouping, the tones
+ * are not symmetric around DC: The negative tones are spaced from the
+ * band edge and the positive tones from DC, with the remaining edge and
+ * DC-adjacent tones added explicitly.
+ */
+pub fn ht_feedback_tones(bandwidth: Bandwidth, ng: usize) -> Vec<i16> {
+    match bandwidth {
+        Bandwidth::Bw20 if ng > 1 => (-28..=-2)
+            .step_by(ng)
+            .chain([-1])
+            .chain((1..=27).step_by(ng))
+            .chain([28])
+            .collect(),
+        Bandwidth::Bw20 | Bandwidth::Bw40 => vht_feedback_tones(bandwidth, ng),
+        _ => Vec::new(), // HT ends at 40 MHz
+    }
+}
+
+/**
+ * Get the feedback tones of EHT compressed beamforming for a grouping Ng
+ * of 4 or 16 and the Partial BW Info field of the EHT MIMO Control.
+ *
+ * The first bit of the field selects the resolution of the bitmap in the
+ * remaining 8 bits, 20 MHz (up to 160 MHz) or 40 MHz (320 MHz). Every set
+ * bit includes the feedback tones of one subchannel, starting at the
+ * lowest frequency. EHT shares the HE tone plan per 80 MHz segment.
+ */
+pub fn eht_feedback_tones(
+    bandwidth: Bandwidth,
+    ng: usize,
+    partial_bw_info: u16,
+) -> Result<Vec<i16>, BfaExtractionError> {
+    const TONES_PER_20MHZ: i16 = 256;
+    const MAX_SUBCHANNELS: i16 = 8;
+
+    let subchannel_width = match partial_bw_info & 1 {
+        0 => TONES_PER_20MHZ,
+        _ => 2 * TONES_PER_20MHZ,
+    };
+    let bitmap = partial_bw_info >> 1;
+    let num_subchannels = (bandwidth.to_mhz() / 20) as i16 * TONES_PER_20MHZ / subchannel_width;
+
+    if bitmap == 0
+        || !(1..=MAX_SUBCHANNELS).contains(&num_subchannels)
+        || bitmap >> num_subchannels != 0
+    {
+        return Err(BfaExtractionError::InvalidPartialBandwidth {
+            partial_bw_info,
+            bandwidth_mhz: bandwidth.to_mhz(),
+        });
+    }
+
+    // Subchannels are counted from the lowest tone of the band
+    let band_start = -num_subchannels * subchannel_width / 2;
+    Ok(full_band_tones(bandwidth, ng)
+        .into_iter()
+        .filter(|&tone| {
+            let subchannel = (tone - band_start) / subchannel_width;
+            bitmap & (1 << subchannel) != 0
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_band_tone_count() {
+        assert_eq!(full_band_tones(Bandwidth::Bw20, 4).len(), 64);
+        assert_eq!(full_band_tones(Bandwidth::Bw40, 4).len(), 122);
+        assert_eq!(full_band_tones(Bandwidth::Bw80, 4).len(), 250);
+        assert_eq!(full_band_tones(Bandwidth::Bw160, 4).len(), 500);
+        assert_eq!(full_band_tones(Bandwidth::Bw20, 16).len(), 20);
+        assert_eq!(full_band_tones(Bandwidth::Bw40, 16).len(), 32);
+        assert_eq!(full_band_tones(Bandwidth::Bw80, 16).len(), 64);
+        assert_eq!(full_band_tones(Bandwidth::Bw160, 16).len(), 128);
+    }
+
+    #[test]
+    fn full_band_tones_20mhz() {
+        let tones = full_band_tones(Bandwidth::Bw20, 16);
+        assert_eq!(
+            tones,
+            vec![
+                -122, -116, -100, -84, -68, -52, -36, -20, -4, -2, 2, 4, 20, 36, 52, 68, 84, 100,
+                116, 122
+            ]
+        );
+
+        let tones = full_band_tones(Bandwidth::Bw20, 4);
+        assert_eq!(tones[..3], [-122, -120, -116]);
+        assert_eq!(tones[30..34], [-4, -2, 2, 4]);
+        assert_eq!(tones[61..], [116, 120, 122]);
+    }
+
+    #[test]
+    fn partial_band_tones() {
+        // Full band spans equal the full band tones
+        let tones = feedback_tones(Bandwidth::Bw20, 4, 0, 8).unwrap();
+        assert_eq!(tones, full_band_tones(Bandwidth::Bw20, 4));
+        let tones = feedback_tones(Bandwidth::Bw160, 16, 0, 73).unwrap();
+        assert_eq!(tones, full_band_tones(Bandwidth::Bw160, 16));
+
+        // First 26-tone RU in 20 MHz (-121 to -96)
+        let tones = feedback_tones(Bandwidth::Bw20, 4, 0, 0).unwrap();
+        assert_eq!(tones, vec![-122, -120, -116, -112, -108, -104, -100, -96]);
+
+        // Center RU in 80 MHz (-16 to 16) with Ng 16
+        let tones = feedback_tones(Bandwidth::Bw80, 16, 18, 18).unwrap();
+        assert_eq!(tones, vec![-20, -4, 4, 20]);
+
+        // Upper 80 MHz segment of 160 MHz
+        let tones = feedback_tones(Bandwidth::Bw160, 4, 37, 73).unwrap();
+        assert_eq!(tones.len(), 250);
+        assert_eq!(tones[0], 12);
+    }
+
+    #[test]
+    fn vht_tone_count() {
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw20, 1).len(), 56);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw20, 2).len(), 30);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw20, 4).len(), 16);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw40, 1).len(), 114);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw40, 2).len(), 58);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw40, 4).len(), 30);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw80, 1).len(), 242);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw80, 2).len(), 122);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw80, 4).len(), 62);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw160, 1).len(), 484);
+        assert_eq!(vht_feedback_tones(Bandwidth::Bw160, 4).len(), 124);
+    }
+
+    #[test]
+    fn vht_tones() {
+        assert_eq!(
+            vht_feedback_tones(Bandwidth::Bw20, 4),
+            vec![-28, -24, -20, -16, -12, -8, -4, -1, 1, 4, 8, 12, 16, 20, 24, 28]
+        );
+        assert_eq!(
+            vht_feedback_tones(Bandwidth::Bw40, 4)[13..17],
+            [-6, -2, 2, 6]
+        );
+
+        let tones = vht_feedback_tones(Bandwidth::Bw160, 1);
+        assert_eq!(tones[..2], [-250, -249]);
+        assert_eq!(tones[120..122], [-130, -126]);
+        assert_eq!(tones[241..243], [-6, 6]);
+    }
+
+    #[test]
+    fn ht_tones() {
+        assert_eq!(ht_feedback_tones(Bandwidth::Bw20, 1).len(), 56);
+        assert_eq!(ht_feedback_tones(Bandwidth::Bw20, 2).len(), 30);
+        assert_eq!(ht_feedback_tones(Bandwidth::Bw40, 2).len(), 58);
+        assert_eq!(ht_feedback_tones(Bandwidth::Bw40, 4).len(), 30);
+        assert!(ht_feedback_tones(Bandwidth::Bw80, 1).is_empty());
+
+        assert_eq!(
+            ht_feedback_tones(Bandwidth::Bw20, 4),
+            vec![-28, -24, -20, -16, -12, -8, -4, -1, 1, 5, 9, 13, 17, 21, 25, 28]
+        );
+    }
+
+    #[test]
+    fn eht_tones() {
+        // Full band
+        let tones = eht_feedback_tones(Bandwidth::Bw80, 4, 0b11110).unwrap();
+        assert_eq!(tones, full_band_tones(Bandwidth::Bw80, 4));
+        let tones = eht_feedback_tones(Bandwidth::Bw320, 4, 0b111111111).unwrap();
+        assert_eq!(tones.len(), 1000);
+        assert_eq!(tones[..2], [-2036, -2032]);
+        assert_eq!(tones[499..501], [-12, 12]);
+
+        // Second 20 MHz subchannel of 40 MHz
+        let tones = eht_feedback_tones(Bandwidth::Bw40, 16, 0b100).unwrap();
+        assert_eq!(tones[0], 4);
+        assert_eq!(tones.len(), 16);
+
+        // Subchannel beyond the bandwidth or none at all
+        assert!(matches!(
+            eht_feedback_tones(Bandwidth::Bw40, 4, 0b1000),
+            Err(BfaExtractionError::InvalidPartialBandwidth {
+                partial_bw_info: 0b1000,
+                bandwidth_mhz: 40
+            })
+        ));
+        assert!(eht_feedback_tones(Bandwidth::Bw20, 4, 0).is_err());
+    }
+
+    #[test]
+    fn ru_span_size() {
+        assert_eq!(check_ru_span(Bandwidth::Bw20, 0, 8).unwrap(), 9);
+        assert_eq!(check_ru_span(Bandwidth::Bw160, 36, 73).unwrap(), 38);
+        assert!(check_ru_span(Bandwidth::Bw80, 0, 37).is_err());
+    }
+
+    #[test]
+    fn invalid_ru_span() {
+        assert!(matches!(
+            feedback_tones(Bandwidth::Bw20, 4, 0, 9),
+            Err(BfaExtractionError::InvalidRuSpan { .. })
+        ));
+        assert!(matches!(
+            feedback_tones(Bandwidth::Bw40, 4, 5, 2),
+            Err(BfaExtractionError::InvalidRuSpan { .. })
+        ));
+    }
+}
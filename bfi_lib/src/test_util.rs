@@ -0,0 +1,31 @@
+/** ------------------------------------------------------------
+ * Fixtures shared by the unit tests.
+ * ------------------------------------------------------------- */
+use crate::action_frame::PhyGeneration;
+use crate::angles::AngleLayout;
+use crate::bfi_data::SinglePacketBfiData;
+use crate::mac_header::MacAddress;
+use crate::radiotap::RadiotapHeader;
+
+/**
+ * HE report without angles, sent by the station with the address
+ * [station; 6] to the beamformer [0xaa; 6]
+ */
+pub fn report(timestamp: f64, token_number: u8, station: u8) -> SinglePacketBfiData {
+    SinglePacketBfiData {
+        timestamp,
+        token_number,
+        phy_generation: PhyGeneration::He,
+        bfa_angles: Vec::new(),
+        angle_layout: AngleLayout::default(),
+        delta_snr: Vec::new(),
+        tone_indices: Vec::new(),
+        stream_snr: Vec::new(),
+        radiotap: RadiotapHeader::default(),
+        transmitter: MacAddress([station; 6]),
+        receiver: MacAddress([0xaa; 6]),
+        sequence_number: 0,
+        sounding: None,
+        fcs_valid: Some(true),
+    }
+}
@@ -8,6 +8,22 @@ pub fn get_u32_from_bytes(byte_stream: &[u8]) -> u32 {
     u32::from_ne_bytes(buffer)
 }
 
+/**
+ * Round an offset up to the next multiple of align (a power of two)
+ */
+pub fn align_up(offset: usize, align: usize) -> usize {
+    (offset + align - 1) & !(align - 1)
+}
+
+/**
+ * Split bytes into 4 bit values, starting at the LSB of every byte
+ */
+pub fn nibbles(byte_stream: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    byte_stream
+        .iter()
+        .flat_map(|&byte| [byte & 0x0f, byte >> 4])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +53,20 @@ mod tests {
         let result = get_u32_from_bytes(byte_stream);
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn align_offsets() {
+        assert_eq!(align_up(0, 4), 0);
+        assert_eq!(align_up(1, 4), 4);
+        assert_eq!(align_up(24, 4), 24);
+        assert_eq!(align_up(26, 4), 28);
+        assert_eq!(align_up(9, 8), 16);
+        assert_eq!(align_up(9, 1), 9);
+    }
+
+    #[test]
+    fn nibble_split() {
+        let result: Vec<u8> = nibbles(&[0x21, 0xf0]).collect();
+        assert_eq!(result, vec![1, 2, 0, 15]);
+    }
 }
@@ -0,0 +1,304 @@
+/** ------------------------------------------------------------
+ * Reconstruction of the beamforming matrix V from the Givens angles.
+ * ------------------------------------------------------------- */
+use crate::angles::AngleLayout;
+use crate::bfi_data::ExtractedBfiData;
+use crate::errors::BfaExtractionError;
+use crate::extract_bfa::{pack_bfa, ExtractionConfig};
+use std::f64::consts::PI;
+
+/**
+ * Complex number as (real, imaginary) part
+ */
+type Complex = (f64, f64);
+
+fn mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+/**
+ * Reconstruct the Nr x Nc matrix V of a single subcarrier from its angles
+ * in radians, ordered as in the compressed report
+ *
+ * V is the product over the columns i up to min(Nc, Nr - 1) of
+ * D_i(e^jphi_{i,i} .. e^jphi_{Nr-1,i}, 1) times the Givens rotations
+ * G_{l,i}^T(psi_{l,i}) for l from i + 1 to Nr, applied to the Nr x Nc
+ * identity. See IEEE 802.11ax Section 19.3.12.3.6.
+ *
+ * \returns V in row-major order as interleaved real and imaginary parts
+ */
+pub fn reconstruct_v(angles: &[f64], nr: usize, nc: usize) -> Vec<f64> {
+    let mut v: Vec<Vec<Complex>> = (0..nr)
+        .map(|row| {
+            (0..nc)
+                .map(|col| ((row == col) as u8 as f64, 0.0))
+                .collect()
+        })
+        .collect();
+
+    // Offset of the angles of every column: Nr - i phi and psi angles each
+    let num_columns = nc.min(nr - 1);
+    let offsets: Vec<usize> = (0..num_columns)
+        .scan(0, |offset, i| {
+            let start = *offset;
+            *offset += 2 * (nr - 1 - i);
+            Some(start)
+        })
+        .collect();
+
+    // Apply the factors from the right, starting with the last column
+    for i in (0..num_columns).rev() {
+        let num_rotations = nr - 1 - i;
+        let phi = &angles[offsets[i]..offsets[i] + num_rotations];
+        let psi = &angles[offsets[i] + num_rotations..offsets[i] + 2 * num_rotations];
+
+        // The rightmost rotation G_{Nr,i}^T comes first
+        for l in (i + 1..nr).rev() {
+            let (sin, cos) = psi[l - i - 1].sin_cos();
+            let (upper, lower) = v.split_at_mut(l);
+            for (a, b) in upper[i].iter_mut().zip(lower[0].iter_mut()) {
+                (*a, *b) = (
+                    (cos * a.0 - sin * b.0, cos * a.1 - sin * b.1),
+                    (sin * a.0 + cos * b.0, sin * a.1 + cos * b.1),
+                );
+            }
+        }
+
+        for (row, &phi) in (i..nr - 1).zip(phi) {
+            let (sin, cos) = phi.sin_cos();
+            for value in v[row].iter_mut() {
+                *value = mul(*value, (cos, sin));
+            }
+        }
+    }
+
+    v.into_iter()
+        .flatten()
+        .flat_map(|(re, im)| [re, im])
+        .collect()
+}
+
+/**
+ * Decompose the Nr x Nc matrix V of a single subcarrier into its angles
+ * in radians, the inverse of reconstruct_v
+ *
+ * V is given in row-major order as interleaved real and imaginary parts.
+ * The phase of every column is first chosen such that the last row is
+ * real and non-negative, as this phase is not part of the feedback. Then
+ * for every column i, D_i^H removes the phases of the rows i to Nr - 1
+ * and the Givens rotations G_{l,i} zero the entries below the diagonal.
+ * See IEEE 802.11ax Section 19.3.12.3.6.
+ */
+pub fn decompose_v(v: &[f64], nr: usize, nc: usize) -> Vec<f64> {
+    let mut v: Vec<Vec<Complex>> = v
+        .chunks_exact(2 * nc)
+        .map(|row| row.chunks_exact(2).map(|c| (c[0], c[1])).collect())
+        .collect();
+
+    for col in 0..nc {
+        let (re, im) = v[nr - 1][col];
+        let (sin, cos) = (-im.atan2(re)).sin_cos();
+        for row in v.iter_mut() {
+            row[col] = mul(row[col], (cos, sin));
+        }
+    }
+
+    let mut angles = Vec::new();
+    for i in 0..nc.min(nr - 1) {
+        for row in v.iter_mut().take(nr - 1).skip(i) {
+            let (re, im) = row[i];
+            let phi = im.atan2(re).rem_euclid(2.0 * PI);
+            let (sin, cos) = (-phi).sin_cos();
+            for value in row.iter_mut() {
+                *value = mul(*value, (cos, sin));
+            }
+            angles.push(phi);
+        }
+
+        for l in i + 1..nr {
+            let psi = v[l][i].0.atan2(v[i][i].0);
+            let (sin, cos) = psi.sin_cos();
+            let (upper, lower) = v.split_at_mut(l);
+            for (a, b) in upper[i].iter_mut().zip(lower[0].iter_mut()) {
+                (*a, *b) = (
+                    (cos * a.0 + sin * b.0, cos * a.1 + sin * b.1),
+                    (cos * b.0 - sin * a.0, cos * b.1 - sin * a.1),
+                );
+            }
+            angles.push(psi);
+        }
+    }
+
+    angles
+}
+
+/**
+ * Compress the matrices V of all subcarriers of a report into the
+ * compressed beamforming report bitstream
+ *
+ * Every V is decomposed into its angles, which are quantized with the bit
+ * sizes of the extraction config and packed like in a captured report.
+ */
+pub fn compress_v(
+    v_matrices: &[Vec<f64>],
+    extraction_config: &ExtractionConfig,
+) -> Result<Vec<u8>, BfaExtractionError> {
+    let layout = extraction_config.angle_layout();
+    let expected = 2 * layout.nr * layout.nc;
+    if let Some(v) = v_matrices.iter().find(|v| v.len() != expected) {
+        return Err(BfaExtractionError::InvalidReportShape {
+            field: "v_matrix",
+            expected,
+            given: v.len(),
+        });
+    }
+
+    let bfa_radians = layout.angles_from_v(v_matrices);
+    pack_bfa(&layout.quantize(&bfa_radians), extraction_config)
+}
+
+impl AngleLayout {
+    /**
+     * Decompose V of every subcarrier of a report into its angles in
+     * radians
+     */
+    pub fn angles_from_v(&self, v_matrices: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        v_matrices
+            .iter()
+            .map(|v| decompose_v(v, self.nr, self.nc))
+            .collect()
+    }
+
+    /**
+     * Reconstruct V of every subcarrier of a report from its angles in
+     * radians
+     */
+    pub fn v_matrices(&self, bfa_radians: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        bfa_radians
+            .iter()
+            .map(|angles| reconstruct_v(angles, self.nr, self.nc))
+            .collect()
+    }
+}
+
+impl ExtractedBfiData {
+    /**
+     * Reconstruct V of every subcarrier of all reports, as interleaved
+     * real and imaginary parts in row-major order
+     */
+    pub fn v_matrices(&self) -> Vec<Vec<Vec<f64>>> {
+        self.bfa_radians()
+            .iter()
+            .zip(&self.angle_layouts)
+            .map(|(bfa_radians, layout)| layout.v_matrices(bfa_radians))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::he_mimo_ctrl::HeMimoControl;
+
+    fn assert_close(result: &[f64], expected: &[f64]) {
+        assert_eq!(result.len(), expected.len());
+        for (r, e) in result.iter().zip(expected) {
+            assert!((r - e).abs() < 1e-12, "{result:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn reconstruct_2x1() {
+        // V = [e^jphi cos(psi), sin(psi)]
+        let v = reconstruct_v(&[PI / 2.0, PI / 3.0], 2, 1);
+        assert_close(&v, &[0.0, 0.5, 3f64.sqrt() / 2.0, 0.0]);
+    }
+
+    #[test]
+    fn reconstruct_2x2() {
+        // The second column is orthogonal to the first one
+        let v = reconstruct_v(&[PI, PI / 4.0], 2, 2);
+        let s = 0.5f64.sqrt();
+        assert_close(&v, &[-s, 0.0, s, 0.0, s, 0.0, s, 0.0]);
+    }
+
+    #[test]
+    fn orthonormal_columns() {
+        // Nr 4, Nc 2: 3 phi, 3 psi, 2 phi, 2 psi
+        let angles = [0.3, 1.7, 4.0, 0.2, 1.1, 0.7, 2.5, 5.9, 0.4, 1.3];
+        let (nr, nc) = (4, 2);
+        let v = reconstruct_v(&angles, nr, nc);
+        let at = |row: usize, col: usize| (v[2 * (row * nc + col)], v[2 * (row * nc + col) + 1]);
+
+        for a in 0..nc {
+            for b in 0..nc {
+                // Inner product of columns a and b
+                let (re, im) = (0..nr).fold((0.0, 0.0), |sum, row| {
+                    let (x, y) = (at(row, a), at(row, b));
+                    let p = mul((x.0, -x.1), y);
+                    (sum.0 + p.0, sum.1 + p.1)
+                });
+                assert!((re - (a == b) as u8 as f64).abs() < 1e-12);
+                assert!(im.abs() < 1e-12);
+            }
+        }
+
+        // The last row of V is real and non-negative
+        for col in 0..nc {
+            assert!(at(nr - 1, col).1.abs() < 1e-12);
+            assert!(at(nr - 1, col).0 >= 0.0);
+        }
+    }
+
+    #[test]
+    fn decompose_reconstructed_v() {
+        let angles = [0.3, 1.7, 4.0, 0.2, 1.1, 0.7, 2.5, 5.9, 0.4, 1.3];
+        let v = reconstruct_v(&angles, 4, 2);
+        assert_close(&decompose_v(&v, 4, 2), &angles);
+
+        // A common phase per column does not change the angles
+        let phases = [(0.8f64.cos(), 0.8f64.sin()), (1.3f64.cos(), -1.3f64.sin())];
+        let rotated: Vec<f64> = v
+            .chunks_exact(2)
+            .enumerate()
+            .flat_map(|(i, c)| {
+                let (re, im) = mul((c[0], c[1]), phases[i % 2]);
+                [re, im]
+            })
+            .collect();
+        assert_close(&decompose_v(&rotated, 4, 2), &angles);
+    }
+
+    #[test]
+    fn compress_v_round_trip() {
+        // HE 20 MHz, Nr 4, Nc 2, Ng 4, codebook 1: 64 subcarriers
+        let byte_stream: &[u8] = &[0b00011001, 0b10000010, 0b00000000, 0b11000100, 0b00001101];
+        let he_mimo_control = HeMimoControl::from_buf(byte_stream).unwrap();
+        let config = ExtractionConfig::from_he_mimo_ctrl(&he_mimo_control).unwrap();
+        let layout = config.angle_layout();
+
+        let bfa_angles: Vec<Vec<u16>> = (0..config.num_subcarrier as u16)
+            .map(|i| {
+                config
+                    .bitfield_pattern
+                    .iter()
+                    .map(|&bits| (i * 5 + 3) % (1 << bits))
+                    .collect()
+            })
+            .collect();
+        let v_matrices = layout.v_matrices(&layout.dequantize(&bfa_angles));
+
+        assert_eq!(
+            compress_v(&v_matrices, &config).unwrap(),
+            pack_bfa(&bfa_angles, &config).unwrap()
+        );
+        assert!(matches!(
+            compress_v(&[vec![0.0; 4]], &config),
+            Err(BfaExtractionError::InvalidReportShape {
+                field: "v_matrix",
+                expected: 16,
+                given: 4
+            })
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+/** ------------------------------------------------------------
+ * VHT Mimo Control Header extraction.
+ * ------------------------------------------------------------- */
+use crate::errors::BfaExtractionError;
+use crate::he_mimo_ctrl::Bandwidth;
+use bilge::prelude::*;
+
+/**
+ * Full VHT Mimo Control fields
+ */
+#[bitsize(24)]
+#[derive(FromBits, DebugBits)]
+pub struct VhtMimoControl {
+    pub nc_index: u3,
+    pub nr_index: u3,
+    pub bandwidth_index: u2,
+    pub grouping: u2,
+    pub codebook_info: u1,
+    pub feedback_type: u1,
+    pub remaining_feedback_segments: u3,
+    pub first_feedback_segments: u1,
+    pub reserved: u2,
+    pub dialog_token_number: u6,
+}
+
+/**
+ * Length of the VHT MIMO Control field in bytes
+ */
+pub const VHT_MIMO_CONTROL_LENGTH: usize = 3;
+
+/**
+ * Extract VhtMimoControl from 3 bytes in buffer
+ */
+impl VhtMimoControl {
+    pub fn from_buf(buf: &[u8]) -> Result<Self, BfaExtractionError> {
+        if buf.len() < VHT_MIMO_CONTROL_LENGTH {
+            return Err(BfaExtractionError::TruncatedFrame {
+                required: VHT_MIMO_CONTROL_LENGTH,
+                available: buf.len(),
+            });
+        }
+
+        let value = UInt::<u32, 24>::new(
+            (buf[0] as u32) | ((buf[1] as u32) << 8) | ((buf[2] as u32) << 16),
+        );
+        Ok(VhtMimoControl::from(value))
+    }
+
+    pub fn bandwidth(&self) -> Bandwidth {
+        Bandwidth::from_u2(self.bandwidth_index())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vht_mimo_ctrl_extraction() {
+        // 1101 1100 1000 0100 1001 0001 = 0xdc8491
+        // VHT MIMO Control:
+        // .... .... .... .... .... .001 = Nc Index: 2 Columns (1)
+        // .... .... .... .... ..01 0... = Nr Index: 3 Rows (2)
+        // .... .... .... .... 10.. .... = Channel Width: 80 MHz (2)
+        // .... .... .... ..00 .... .... = Grouping: No Grouping (0)
+        // .... .... .... .1.. .... .... = Codebook Information: 1
+        // .... .... .... 0... .... .... = Feedback Type: SU (0)
+        // .... .... .000 .... .... .... = Remaining Feedback Segments: 0
+        // .... .... 1... .... .... .... = First Feedback Segment: 1
+        // .... ..00 .... .... .... .... = Reserved: 0
+        // 1101 11.. .... .... .... .... = Sounding Dialog Token Number: 55
+
+        // bytestream (little endian)
+        let byte_stream: &[u8] = &[0b10010001, 0b10000100, 0b11011100];
+
+        let result = VhtMimoControl::from_buf(byte_stream).unwrap();
+        assert_eq!(result.nc_index(), UInt::<u8, 3>::new(1));
+        assert_eq!(result.nr_index(), UInt::<u8, 3>::new(2));
+        assert_eq!(result.bandwidth(), Bandwidth::Bw80);
+        assert_eq!(result.grouping(), UInt::<u8, 2>::new(0));
+        assert_eq!(result.codebook_info(), UInt::<u8, 1>::new(1));
+        assert_eq!(result.feedback_type(), UInt::<u8, 1>::new(0));
+        assert_eq!(result.remaining_feedback_segments(), UInt::<u8, 3>::new(0));
+        assert_eq!(result.first_feedback_segments(), UInt::<u8, 1>::new(1));
+        assert_eq!(result.dialog_token_number(), UInt::<u8, 6>::new(55));
+
+        assert!(matches!(
+            VhtMimoControl::from_buf(&byte_stream[..2]),
+            Err(BfaExtractionError::TruncatedFrame {
+                required: 3,
+                available: 2
+            })
+        ));
+    }
+}
@@ -1,6 +1,298 @@
-use bfi_lib::{extract_from_capture, ExtractedBfiData};
-use numpy::{PyArray1, PyArray3};
+use bfi_lib::{
+    extract_from_capture, extract_from_capture_with_options, CqiReport, ExtractedBfiData,
+    ExtractionMode, ExtractionOptions, ExtractionReport, FcsPolicy,
+};
+use bfi_lib::{MacAddress, SoundingInfo, SoundingSession, DEFAULT_SESSION_WINDOW};
+use numpy::{Complex64, PyArray1, PyArray2, PyArray3, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+/**
+ * Tuple of numpy arrays (and lists of PHY generations, soundings, sounding
+ * sequence numbers and FCS validity) returned to python, one entry per
+ * packet, followed by the list of CQI-only reports. It is built as a
+ * PyTuple, since pyo3 converts native tuples of at most 12 elements.
+ */
+type PyBfiData<'py> = Bound<'py, PyTuple>;
+
+/**
+ * Convert MAC addresses into an (N, 6) array of address bytes
+ */
+fn addresses_to_pyarray<'py>(
+    py: Python<'py>,
+    addresses: &[MacAddress],
+) -> Bound<'py, PyArray2<u8>> {
+    let rows: Vec<Vec<u8>> = addresses.iter().map(|address| address.0.to_vec()).collect();
+    PyArray2::from_vec2_bound(py, &rows).unwrap()
+}
+
+/**
+ * Convert the sounding context of a report into a dict, None if the NDPA
+ * of the report was not captured
+ */
+fn sounding_to_py<'py>(
+    py: Python<'py>,
+    sounding: &Option<SoundingInfo>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let Some(sounding) = sounding else {
+        return Ok(None);
+    };
+
+    let requested = match &sounding.requested {
+        Some(request) => {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("aid", request.aid)?;
+            dict.set_item("feedback_type", request.feedback_type)?;
+            dict.set_item("nc_index", request.nc_index)?;
+            dict.set_item("grouping", request.grouping)?;
+            dict.set_item("codebook_info", request.codebook_info)?;
+            dict.set_item("ru_start_index", request.ru_span.map(|(start, _)| start))?;
+            dict.set_item("ru_end_index", request.ru_span.map(|(_, end)| end))?;
+            dict.set_item("partial_bw_info", request.partial_bw_info)?;
+            Some(dict)
+        }
+        None => None,
+    };
+
+    let dict = PyDict::new_bound(py);
+    dict.set_item("ndpa_timestamp", sounding.ndpa_timestamp)?;
+    dict.set_item("beamformer", sounding.beamformer.to_string())?;
+    dict.set_item("requested", requested)?;
+    dict.set_item("parameter_mismatch", sounding.parameter_mismatch)?;
+    Ok(Some(dict))
+}
+
+/**
+ * Convert a CQI-only report into a python dict
+ */
+fn cqi_report_to_py<'py>(py: Python<'py>, report: &CqiReport) -> PyResult<Bound<'py, PyDict>> {
+    let cqi = PyArray2::from_vec2_bound(py, &report.cqi)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    let dict = PyDict::new_bound(py);
+    dict.set_item("timestamp", report.timestamp)?;
+    dict.set_item("token_num", report.token_number)?;
+    dict.set_item("transmitter", report.transmitter.to_string())?;
+    dict.set_item("receiver", report.receiver.to_string())?;
+    dict.set_item("sequence_num", report.sequence_number)?;
+    dict.set_item("ru_start_index", report.ru_start_index)?;
+    dict.set_item("ru_end_index", report.ru_end_index)?;
+    dict.set_item("cqi", cqi)?;
+    dict.set_item("sounding", sounding_to_py(py, &report.sounding)?)?;
+    dict.set_item("fcs_valid", report.fcs_valid)?;
+    Ok(dict)
+}
+
+/**
+ * Convert the delta SNR into a (packets, subcarriers, columns) array in
+ * dB. Reports without delta SNR (all but HE and EHT MU feedback) are
+ * filled with NaN, so mixed SU and MU captures still form one array.
+ */
+fn delta_snr_to_py<'py>(
+    py: Python<'py>,
+    delta_snr: &[Vec<Vec<i8>>],
+) -> PyResult<Bound<'py, PyArray3<f32>>> {
+    let shape = delta_snr
+        .iter()
+        .find(|report| !report.is_empty())
+        .map_or((0, 0), |report| (report.len(), report[0].len()));
+    let padded: Vec<Vec<Vec<f32>>> = delta_snr
+        .iter()
+        .map(|report| match report.is_empty() {
+            true => vec![vec![f32::NAN; shape.1]; shape.0],
+            false => report
+                .iter()
+                .map(|subcarrier| subcarrier.iter().map(|&snr| snr as f32).collect())
+                .collect(),
+        })
+        .collect();
+
+    PyArray3::from_vec3_bound(py, &padded).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/**
+ * Convert extracted data into the tuple of numpy arrays, with the angles
+ * in radians if requested
+ */
+fn data_to_py(
+    py: Python<'_>,
+    extracted_data: ExtractedBfiData,
+    radians: bool,
+) -> PyResult<PyBfiData<'_>> {
+    let sounding_seqs = extracted_data.sounding_sequence().unwrapped_tokens;
+    let bfa_radians = radians.then(|| extracted_data.bfa_radians());
+    let ExtractedBfiData {
+        timestamps,
+        token_nums,
+        bfa_angles,
+        transmitters,
+        receivers,
+        sequence_nums,
+        tone_indices,
+        stream_snr,
+        delta_snr,
+        phy_generations,
+        soundings,
+        fcs_valid,
+        cqi_reports,
+        ..
+    } = extracted_data;
+
+    // All reports need the same dimensions to form a 3D array
+    let bfa_angles = match bfa_radians {
+        Some(bfa_radians) => PyArray3::from_vec3_bound(py, &bfa_radians)
+            .map(|array| array.into_py(py))
+            .map_err(|e| PyValueError::new_err(e.to_string()))?,
+        None => PyArray3::from_vec3_bound(py, &bfa_angles)
+            .map(|array| array.into_py(py))
+            .map_err(|e| PyValueError::new_err(e.to_string()))?,
+    };
+    let tone_indices = PyArray2::from_vec2_bound(py, &tone_indices)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let stream_snr = PyArray2::from_vec2_bound(py, &stream_snr)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let delta_snr = delta_snr_to_py(py, &delta_snr)?;
+    let soundings = soundings
+        .iter()
+        .map(|sounding| sounding_to_py(py, sounding))
+        .collect::<PyResult<Vec<_>>>()?;
+    let cqi_reports = cqi_reports
+        .iter()
+        .map(|report| cqi_report_to_py(py, report))
+        .collect::<PyResult<Vec<_>>>()?;
+    let phy_generations: Vec<String> = phy_generations
+        .iter()
+        .map(|generation| generation.to_string())
+        .collect();
+
+    Ok(PyTuple::new_bound(
+        py,
+        [
+            PyArray1::from_vec_bound(py, timestamps).into_py(py),
+            PyArray1::from_vec_bound(py, token_nums).into_py(py),
+            bfa_angles,
+            addresses_to_pyarray(py, &transmitters).into_py(py),
+            addresses_to_pyarray(py, &receivers).into_py(py),
+            PyArray1::from_vec_bound(py, sequence_nums).into_py(py),
+            tone_indices.into_py(py),
+            stream_snr.into_py(py),
+            delta_snr.into_py(py),
+            phy_generations.into_py(py),
+            soundings.into_py(py),
+            sounding_seqs.into_py(py),
+            fcs_valid.into_py(py),
+            cqi_reports.into_py(py),
+        ],
+    ))
+}
+
+/**
+ * Convert the V matrices of all reports into a complex array of shape
+ * (packets, subcarriers, Nr, Nc)
+ */
+fn v_matrices_to_py<'py>(
+    py: Python<'py>,
+    extracted_data: &ExtractedBfiData,
+) -> PyResult<Bound<'py, PyAny>> {
+    let (subcarriers, nr, nc) = match extracted_data.angle_layouts.first() {
+        Some(layout) => (extracted_data.bfa_angles[0].len(), layout.nr, layout.nc),
+        None => (0, 0, 0),
+    };
+
+    // All reports need the same dimensions to form a 4D array
+    let uniform = extracted_data
+        .angle_layouts
+        .iter()
+        .zip(&extracted_data.bfa_angles)
+        .all(|(layout, bfa_angles)| {
+            (bfa_angles.len(), layout.nr, layout.nc) == (subcarriers, nr, nc)
+        });
+    if !uniform {
+        return Err(PyValueError::new_err(
+            "reports differ in subcarriers, Nr or Nc",
+        ));
+    }
+
+    let values: Vec<Complex64> = extracted_data
+        .v_matrices()
+        .into_iter()
+        .flatten()
+        .flat_map(|v| {
+            v.chunks_exact(2)
+                .map(|part| Complex64::new(part[0], part[1]))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let num_reports = extracted_data.bfa_angles.len();
+    let array = PyArray1::from_vec_bound(py, values);
+    Ok(array
+        .reshape([num_reports, subcarriers, nr, nc])?
+        .into_any())
+}
+
+/**
+ * Convert the extraction report into a python dict
+ */
+fn report_to_py<'py>(py: Python<'py>, report: &ExtractionReport) -> PyResult<Bound<'py, PyDict>> {
+    let skipped_frames = PyDict::new_bound(py);
+    for (reason, count) in &report.skipped_frames {
+        skipped_frames.set_item(reason.to_string(), count)?;
+    }
+
+    let failures = PyDict::new_bound(py);
+    for (kind, stats) in &report.failures {
+        let failure = PyDict::new_bound(py);
+        failure.set_item("count", stats.count)?;
+        failure.set_item("first_indices", stats.first_indices.clone())?;
+        failures.set_item(kind, failure)?;
+    }
+
+    let sounding_stats = PyDict::new_bound(py);
+    for (beamformer, stats) in &report.sounding_stats {
+        let sounding = PyDict::new_bound(py);
+        sounding.set_item("soundings", stats.soundings)?;
+        sounding.set_item("missing", stats.missing)?;
+        sounding.set_item("duplicates", stats.duplicates)?;
+        sounding.set_item("reordered", stats.reordered)?;
+        sounding_stats.set_item(beamformer.to_string(), sounding)?;
+    }
+
+    let dict = PyDict::new_bound(py);
+    dict.set_item("total_packets", report.total_packets)?;
+    dict.set_item("extracted", report.extracted)?;
+    dict.set_item("extracted_cqi", report.extracted_cqi)?;
+    dict.set_item("reassembled", report.reassembled)?;
+    dict.set_item("incomplete_segment_sets", report.incomplete_segment_sets)?;
+    dict.set_item(
+        "out_of_order_segment_sets",
+        report.out_of_order_segment_sets,
+    )?;
+    dict.set_item("ndp_announcements", report.ndp_announcements)?;
+    dict.set_item("mismatched_reports", report.mismatched_reports)?;
+    dict.set_item("bad_fcs_reports", report.bad_fcs_reports)?;
+    dict.set_item("sounding_stats", sounding_stats)?;
+    dict.set_item("skipped_frames", skipped_frames)?;
+    dict.set_item("failures", failures)?;
+    Ok(dict)
+}
+
+/**
+ * Convert a sounding session into a python dict
+ */
+fn session_to_py<'py>(py: Python<'py>, session: &SoundingSession) -> PyResult<Bound<'py, PyDict>> {
+    let stations: Vec<String> = session.stations.iter().map(|s| s.to_string()).collect();
+
+    let dict = PyDict::new_bound(py);
+    dict.set_item("beamformer", session.beamformer.to_string())?;
+    dict.set_item("dialog_token_number", session.dialog_token_number)?;
+    dict.set_item("start_timestamp", session.start_timestamp)?;
+    dict.set_item("end_timestamp", session.end_timestamp)?;
+    dict.set_item("stations", stations)?;
+    dict.set_item("report_indices", session.report_indices.clone())?;
+    Ok(dict)
+}
 
 #[pymodule]
 fn bfi_extract<'py>(_py: Python<'py>, m: &Bound<'py, PyModule>) -> PyResult<()> {
@@ -8,31 +300,164 @@ fn bfi_extract<'py>(_py: Python<'py>, m: &Bound<'py, PyModule>) -> PyResult<()>
      * Extract data from a pcap file
      *
      * \param path: Path to pcap file
+     * \param radians: Return bfa_angles in radians instead of quantized
+     *
+     * \returns A tuple of extracted values, each a numpy array with
+     *          length equal to the number of packets: (timestamps,
+     *          token_nums, bfa_angles, transmitters, receivers,
+     *          sequence_nums, tone_indices, stream_snr, delta_snr,
+     *          phy_generations, soundings, sounding_seqs, fcs_valid,
+     *          cqi_reports). MAC addresses are given as rows of 6
+     *          bytes, tone indices as the signed subcarrier index of
+     *          every row in bfa_angles and stream_snr as the average
+     *          SNR in dB per column. delta_snr holds the delta SNR in
+     *          dB per subcarrier and column of HE and EHT MU feedback,
+     *          and NaN for the other reports. phy_generations is a list
+     *          of "HT", "VHT", "HE" or "EHT". soundings holds a dict
+     *          per report with the ndpa_timestamp, beamformer,
+     *          requested feedback parameters (None if the NDPA polls
+     *          several STAs and the AID of the beamformee is unknown)
+     *          and parameter_mismatch, or None if no NDPA was captured.
+     *          sounding_seqs is a list of the dialog tokens unwrapped
+     *          into a monotonic sequence number per beamformer, None
+     *          for HT reports without dialog token. fcs_valid is a list
+     *          of True, False or None if the FCS could not be verified.
+     *          Frames with a bad FCS are dropped. cqi_reports is a list
+     *          with a dict per CQI-only report, holding the CQI index
+     *          per 26-tone RU and stream as an (RUs, streams) array
+     *          along with the report header and sounding as above.
      *
-     * \returns A tuple of extracted values, each a numpy array
-     *          with length equal to the number of packets.
+     * \raises ValueError if the capture can not be read or contains
+     *         a malformed beamforming report.
      */
     #[allow(dead_code)]
     #[pyfn(m)]
+    #[pyo3(signature = (path, radians = false))]
     fn extract_from_pcap<'py>(
         py: Python<'py>,
         path: &str,
-    ) -> (
-        Bound<'py, PyArray1<f64>>,
-        Bound<'py, PyArray1<u8>>,
-        Bound<'py, PyArray3<u16>>,
-    ) {
-        let ExtractedBfiData {
-            timestamps,
-            token_nums,
-            bfa_angles,
-        } = extract_from_capture(path.into());
+        radians: bool,
+    ) -> PyResult<PyBfiData<'py>> {
+        let extracted_data =
+            extract_from_capture(path.into()).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        data_to_py(py, extracted_data, radians)
+    }
+
+    /**
+     * Extract data from a pcap file, skipping malformed packets
+     *
+     * \param path: Path to pcap file
+     * \param max_failure_indices: Packet indices to keep per error kind
+     * \param keep_bad_fcs: Keep frames with a bad FCS, flagged in fcs_valid
+     * \param radians: Return bfa_angles in radians instead of quantized
+     *
+     * \returns A tuple (data, report) with data as returned by
+     *          extract_from_pcap and a report dict with the keys
+     *          total_packets, extracted, extracted_cqi (CQI-only
+     *          reports), reassembled (reports made up of several
+     *          segments), incomplete_segment_sets,
+     *          out_of_order_segment_sets, ndp_announcements,
+     *          mismatched_reports (reports differing from their NDPA
+     *          request), bad_fcs_reports (kept reports with a bad FCS),
+     *          sounding_stats (soundings, missing, duplicates and
+     *          reordered per beamformer), skipped_frames (count per
+     *          reason) and failures (count and first_indices per error
+     *          kind).
+     */
+    #[allow(dead_code)]
+    #[pyfn(m)]
+    #[pyo3(signature = (path, max_failure_indices = 10, keep_bad_fcs = false, radians = false))]
+    fn extract_from_pcap_lenient<'py>(
+        py: Python<'py>,
+        path: &str,
+        max_failure_indices: usize,
+        keep_bad_fcs: bool,
+        radians: bool,
+    ) -> PyResult<(PyBfiData<'py>, Bound<'py, PyDict>)> {
+        let options = ExtractionOptions {
+            mode: ExtractionMode::Lenient,
+            max_failure_indices,
+            fcs_policy: match keep_bad_fcs {
+                true => FcsPolicy::Keep,
+                false => FcsPolicy::Drop,
+            },
+            ..Default::default()
+        };
+        let (extracted_data, report) = extract_from_capture_with_options(path.into(), &options)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        Ok((
+            data_to_py(py, extracted_data, radians)?,
+            report_to_py(py, &report)?,
+        ))
+    }
+
+    /**
+     * Extract data from a pcap file and group it into sounding sessions
+     *
+     * \param path: Path to pcap file
+     * \param window: Maximum duration of a sounding session in seconds
+     * \param radians: Return bfa_angles in radians instead of quantized
+     *
+     * \returns A tuple (data, sessions) with data as returned by
+     *          extract_from_pcap and a list of session dicts with the
+     *          keys beamformer, dialog_token_number, start_timestamp,
+     *          end_timestamp, stations and report_indices (indices
+     *          into data).
+     *
+     * \raises ValueError if the capture can not be read or contains
+     *         a malformed beamforming report.
+     */
+    #[allow(dead_code)]
+    #[pyfn(m)]
+    #[pyo3(signature = (path, window = DEFAULT_SESSION_WINDOW, radians = false))]
+    fn extract_sessions_from_pcap<'py>(
+        py: Python<'py>,
+        path: &str,
+        window: f64,
+        radians: bool,
+    ) -> PyResult<(PyBfiData<'py>, Vec<Bound<'py, PyDict>>)> {
+        let extracted_data =
+            extract_from_capture(path.into()).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        let sessions = extracted_data
+            .sounding_sessions(window)
+            .iter()
+            .map(|session| session_to_py(py, session))
+            .collect::<PyResult<_>>()?;
+
+        Ok((data_to_py(py, extracted_data, radians)?, sessions))
+    }
+
+    /**
+     * Extract data from a pcap file and reconstruct the beamforming
+     * matrix V of every subcarrier from the compressed angles
+     *
+     * \param path: Path to pcap file
+     * \param radians: Return bfa_angles in radians instead of quantized
+     *
+     * \returns A tuple (data, v) with data as returned by
+     *          extract_from_pcap and v as complex128 array of shape
+     *          (packets, subcarriers, Nr, Nc).
+     *
+     * \raises ValueError if the capture can not be read, contains a
+     *         malformed beamforming report or reports of different
+     *         dimensions.
+     */
+    #[allow(dead_code)]
+    #[pyfn(m)]
+    #[pyo3(signature = (path, radians = false))]
+    fn extract_v_matrices_from_pcap<'py>(
+        py: Python<'py>,
+        path: &str,
+        radians: bool,
+    ) -> PyResult<(PyBfiData<'py>, Bound<'py, PyAny>)> {
+        let extracted_data =
+            extract_from_capture(path.into()).map_err(|e| PyValueError::new_err(e.to_string()))?;
 
-        (
-            PyArray1::from_vec_bound(py, timestamps),
-            PyArray1::from_vec_bound(py, token_nums),
-            PyArray3::from_vec3_bound(py, &bfa_angles).unwrap(),
-        )
+        let v = v_matrices_to_py(py, &extracted_data)?;
+        Ok((data_to_py(py, extracted_data, radians)?, v))
     }
 
     Ok(())